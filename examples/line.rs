@@ -1,8 +1,9 @@
 use avian2d::prelude::*;
 use bevy::prelude::*;
 use bevy_dodgy::agents::{AgentGoal, AgentInfo, AvoidanceOptionsComponent};
+use bevy_dodgy::side::SidePreference;
+use bevy_dodgy::stuck::{StuckDetector, StuckRecovery};
 use bevy_dodgy::DodgyPlugin;
-use dodgy_2d::AvoidanceOptions;
 use rand::Rng;
 
 fn main() {
@@ -19,59 +20,37 @@ fn main() {
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2d);
 
-    let mut rng = rand::thread_rng();
+    let mut rng = rand::rng();
 
     let right_x = 400.0;
     for i in 0..20 {
-        commands
-            .spawn(AgentInfo {
-                radius: 8.0,
-                avoidance_responsibility: rng.gen_range(1.0..2.0),
-                max_speed: 30.0,
-            })
-            .insert(RigidBody::Dynamic)
-            .insert(LockedAxes::ROTATION_LOCKED)
-            .insert(AgentGoal {
-                dest: Vec2::new(right_x + 200.0, 0.0),
-                tolerance: 4.0,
-            })
-            .insert(TransformBundle::from(Transform::from_translation(
-                Vec3::new(right_x + -100.0, -250.0 + 20. * i as f32, 0.0),
-            )))
-            .insert(AvoidanceOptionsComponent(AvoidanceOptions {
-                obstacle_margin: 2.1,
-                time_horizon: 3.0,
-                obstacle_time_horizon: 1.0,
-            }))
-            .insert(CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)))
-            .insert(
-                DebugRender::default().with_collider_color(Srgba::hex("#a52c4c").unwrap().into()),
-            );
+        commands.spawn((
+            AgentInfo::new(8.0, 30.0).with_avoidance_responsibility(rng.random_range(1.0..2.0)),
+            AgentGoal::new(Vec2::new(right_x + 200.0, 0.0), 4.0),
+            Transform::from_xyz(right_x + -100.0, -250.0 + 20. * i as f32, 0.0),
+            AvoidanceOptionsComponent::new(2.1, 3.0, 1.0),
+            StuckDetector::new(2.0, 5.0).with_recovery(StuckRecovery::Perturb {
+                strength: 0.5,
+                duration: 1.0,
+            }),
+            SidePreference::KeepRight,
+            CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)),
+            DebugRender::default().with_collider_color(Srgba::hex("#a52c4c").unwrap().into()),
+        ));
 
-        commands
-            .spawn(AgentInfo {
-                radius: 8.0,
-                avoidance_responsibility: rng.gen_range(1.0..2.0),
-                max_speed: 30.0,
-            })
-            .insert(RigidBody::Dynamic)
-            //.insert(LockedAxes::new().lock_rotation_x().lock_rotation_z())
-            .insert(AgentGoal {
-                dest: Vec2::new(right_x + -200.0, 0.0),
-                tolerance: 4.0,
-            })
-            .insert(TransformBundle::from(Transform::from_translation(
-                Vec3::new(right_x + 100.0, -250.0 + 20. * i as f32, 0.0),
-            )))
-            .insert(AvoidanceOptionsComponent(AvoidanceOptions {
-                obstacle_margin: 2.1,
-                time_horizon: 3.0,
-                obstacle_time_horizon: 1.0,
-            }))
-            .insert(CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)))
-            .insert(
-                DebugRender::default().with_collider_color(Srgba::hex("#2e41a1").unwrap().into()),
-            );
+        commands.spawn((
+            AgentInfo::new(8.0, 30.0).with_avoidance_responsibility(rng.random_range(1.0..2.0)),
+            AgentGoal::new(Vec2::new(right_x + -200.0, 0.0), 4.0),
+            Transform::from_xyz(right_x + 100.0, -250.0 + 20. * i as f32, 0.0),
+            AvoidanceOptionsComponent::new(2.1, 3.0, 1.0),
+            StuckDetector::new(2.0, 5.0).with_recovery(StuckRecovery::Perturb {
+                strength: 0.5,
+                duration: 1.0,
+            }),
+            SidePreference::KeepRight,
+            CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)),
+            DebugRender::default().with_collider_color(Srgba::hex("#2e41a1").unwrap().into()),
+        ));
     }
 
     // Makes agents that have no prediction at all
@@ -123,4 +102,3 @@ fn setup(mut commands: Commands) {
 
      */
 }
-
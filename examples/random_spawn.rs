@@ -1,10 +1,11 @@
 use avian2d::math::Vector;
 use avian2d::prelude::*;
+use bevy::diagnostic::LogDiagnosticsPlugin;
 use bevy::prelude::*;
 use bevy_dodgy::agents::{AgentGoal, AgentInfo, AvoidanceOptionsComponent};
 use bevy_dodgy::debug::DodgyDebugPlugin;
+use bevy_dodgy::diagnostics::DodgyDiagnosticsPlugin;
 use bevy_dodgy::DodgyPlugin;
-use dodgy_2d::AvoidanceOptions;
 use rand::Rng;
 
 fn main() {
@@ -14,65 +15,53 @@ fn main() {
         .add_plugins(PhysicsDebugPlugin::default())
         .add_plugins(DodgyPlugin)
         .add_plugins(DodgyDebugPlugin)
+        .add_plugins(DodgyDiagnosticsPlugin::default())
+        .add_plugins(LogDiagnosticsPlugin::default())
         .add_systems(Startup, setup)
-        .insert_resource(Gravity(Vec3::ZERO))
+        .insert_resource(Gravity(Vec2::ZERO))
         .run();
 }
 
 fn setup(mut commands: Commands) {
-    commands.spawn(Camera3dBundle {
-        transform: Transform::from_xyz(0.0, 1200.0, 0.0).looking_at(Vec3::ZERO, Vec3::Y),
-        ..default()
-    });
+    commands.spawn((Camera2d, Transform::from_scale(Vec3::splat(5.0))));
 
-    let mut rng = rand::thread_rng();
+    let mut rng = rand::rng();
     for _ in 0..2000 {
-        commands
-            .spawn(AgentInfo {
-                radius: 8.0,
-                avoidance_responsibility: 1.0,
-                max_speed: 30.0,
-            })
-            .insert(AvoidanceOptionsComponent(AvoidanceOptions {
-                obstacle_margin: 8.1,
-                time_horizon: 5.0,
-                obstacle_time_horizon: 3.0,
-            }))
-            .insert(RigidBody::Dynamic)
-            .insert(LockedAxes::new().lock_rotation_x().lock_rotation_z())
-            .insert(AgentGoal(Vec3::new(
-                rng.random_range(-2000.0..2000.0),
-                0.0,
-                rng.random_range(-2000.0..2000.0),
-            )))
-            .insert(TransformBundle::from(Transform::from_translation(
-                Vec3::new(
+        commands.spawn((
+            AgentInfo::new(8.0, 30.0),
+            AvoidanceOptionsComponent::new(8.1, 5.0, 3.0),
+            AgentGoal::new(
+                Vec2::new(
                     rng.random_range(-2000.0..2000.0),
-                    0.0,
                     rng.random_range(-2000.0..2000.0),
                 ),
-            )))
-            .insert(CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)))
-            .insert(
-                DebugRender::default().with_collider_color(Srgba::hex("#a52c4c").unwrap().into()),
-            );
+                4.0,
+            ),
+            Transform::from_xyz(
+                rng.random_range(-2000.0..2000.0),
+                rng.random_range(-2000.0..2000.0),
+                0.0,
+            ),
+            CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)),
+            DebugRender::default().with_collider_color(Srgba::hex("#a52c4c").unwrap().into()),
+        ));
     }
 
     commands.spawn((
         RigidBody::Static,
-        TransformBundle::from(Transform::from_translation(Vec3::new(250.0, 0.0, 50.0))),
-        Collider::cuboid(150.0, 1.0, 150.0),
+        Transform::from_xyz(250.0, 50.0, 0.0),
+        Collider::rectangle(150.0, 150.0),
         CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)),
         DebugRender::default().with_collider_color(Srgba::hex("#b86830").unwrap().into()),
     ));
 
     commands.spawn((
         RigidBody::Static,
-        TransformBundle::from(Transform::from_translation(Vec3::new(-300.0, 0.0, 600.0))),
+        Transform::from_xyz(-300.0, 600.0, 0.0),
         Collider::triangle(
-            Vector::new(0.0, 0.0, 0.0),
-            Vector::new(100.0, 0.0, 200.0),
-            Vector::new(200.0, 0.0, 0.0),
+            Vector::new(0.0, 0.0),
+            Vector::new(200.0, 0.0),
+            Vector::new(100.0, 200.0),
         ),
         CollisionLayers::new(LayerMask(0b1111), LayerMask(0b1111)),
         DebugRender::default().with_collider_color(Srgba::hex("#b86830").unwrap().into()),
@@ -1,7 +1,19 @@
-use avian2d::prelude::LinearVelocity;
+use crate::constraints::AvoidanceConstraints;
+use crate::flocking::Flocking;
+use crate::groups::AvoidanceGroup;
+use crate::side::SidePreference;
+use crate::sleep::{AgentSleepTimer, AgentSleeping};
+use crate::stuck::StuckRecoveryState;
+use avian2d::prelude::{Collider, LinearVelocity, LockedAxes, RigidBody};
+use bevy::ecs::component::ComponentId;
 use bevy::ecs::query::QueryData;
+use bevy::ecs::world::DeferredWorld;
 use bevy::math::Vec3Swizzles;
-use bevy::prelude::{Component, Deref, DerefMut, Entity, Transform, Vec2};
+use bevy::prelude::{
+    Component, Deref, DerefMut, Entity, Has, Reflect, ReflectComponent, ReflectDefault, Transform,
+    Vec2,
+};
+use bevy::reflect::reflect_remote;
 use dodgy_2d::{Agent, AvoidanceOptions};
 
 /// A QueryData used by the rvo_avoidance system to simplify queries.
@@ -14,6 +26,19 @@ pub struct AgentQueryData {
     pub transform: &'static Transform,
     pub goal: Option<&'static AgentGoal>,
     pub options: &'static AvoidanceOptionsComponent,
+    pub stable_id: Option<&'static StableId>,
+    pub sleeping: Has<AgentSleeping>,
+    pub recovery: Option<&'static StuckRecoveryState>,
+    pub side_preference: Option<&'static SidePreference>,
+    pub group: Option<&'static AvoidanceGroup>,
+    pub flocking: Option<&'static Flocking>,
+}
+
+impl AgentQueryDataItem<'_> {
+    /// The key used to order agents in [`AvoidanceMode::Deterministic`](crate::AvoidanceMode).
+    pub fn sort_key(&self) -> u64 {
+        StableId::key(self.entity, self.stable_id)
+    }
 }
 
 ///
@@ -26,16 +51,64 @@ pub struct AgentQueryDataMut {
     pub linvel: &'static mut LinearVelocity,
     pub goal: Option<&'static AgentGoal>,
     pub options: &'static AvoidanceOptionsComponent,
+    pub stable_id: Option<&'static StableId>,
+    pub result: &'static mut AvoidanceResult,
+    pub sleeping: Has<AgentSleeping>,
+    pub sleep_timer: &'static mut AgentSleepTimer,
+    pub recovery: Option<&'static StuckRecoveryState>,
+    pub group: Option<&'static AvoidanceGroup>,
+    pub neighbours: Option<&'static mut AvoidanceNeighbours>,
+    pub constraints: Option<&'static mut AvoidanceConstraints>,
 }
 
-#[derive(Component, Debug)]
+/// An identifier shared by all peers of a lockstep simulation.
+///
+/// Entity ids are not guaranteed to match between clients, so the deterministic
+/// avoidance mode orders agents and obstacles by this id when present.
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[reflect(Component, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StableId(pub u64);
+
+impl StableId {
+    /// Returns the ordering key of an entity, falling back to its bits without a [`StableId`].
+    pub fn key(entity: Entity, stable_id: Option<&StableId>) -> u64 {
+        stable_id.map_or(entity.to_bits(), |id| id.0)
+    }
+}
+
+#[derive(Component, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Component, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AgentGoal{
     pub dest: Vec2,
     pub tolerance: f32,
 }
 
+impl AgentGoal {
+    pub fn new(dest: Vec2, tolerance: f32) -> AgentGoal {
+        AgentGoal { dest, tolerance }
+    }
+}
+
 /// Represents an agent in the simulation
-#[derive(Component, Clone, PartialEq, Debug)]
+///
+/// Inserting it is enough to make an entity a fully configured agent: the rigid body,
+/// rotation lock, velocity and default [`AvoidanceOptionsComponent`] are required
+/// components, and a circle collider matching the radius is created unless a
+/// [`Collider`] is provided.
+#[derive(Component, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Component, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[require(
+    AvoidanceOptionsComponent,
+    RigidBody(|| RigidBody::Dynamic),
+    LockedAxes(|| LockedAxes::ROTATION_LOCKED),
+    LinearVelocity,
+    AvoidanceResult,
+    AgentSleepTimer
+)]
+#[component(on_add = on_add_create_collider)]
 pub struct AgentInfo {
     /// The radius of the agent. Agents will use this to avoid bumping into each
     /// other.
@@ -50,8 +123,101 @@ pub struct AgentInfo {
     pub max_speed: f32,
 }
 
-#[derive(Component, Clone, PartialEq, Debug, Deref, DerefMut)]
-pub struct AvoidanceOptionsComponent(pub AvoidanceOptions);
+impl AgentInfo {
+    /// Creates an agent with an avoidance responsibility of one.
+    pub fn new(radius: f32, max_speed: f32) -> AgentInfo {
+        AgentInfo {
+            radius,
+            avoidance_responsibility: 1.0,
+            max_speed,
+        }
+    }
+
+    pub fn with_avoidance_responsibility(mut self, avoidance_responsibility: f32) -> AgentInfo {
+        self.avoidance_responsibility = avoidance_responsibility;
+        self
+    }
+}
+
+fn on_add_create_collider(mut world: DeferredWorld, entity: Entity, _: ComponentId) {
+    if world.entity(entity).contains::<Collider>() {
+        return;
+    }
+
+    let radius = world
+        .get::<AgentInfo>(entity)
+        .map_or(0.0, |agent| agent.radius);
+    world
+        .commands()
+        .entity(entity)
+        .insert(Collider::circle(radius));
+}
+
+/// The outcome of the last avoidance solve of an agent.
+///
+/// Agents skipped by the [`AvoidanceLod`](crate::lod::AvoidanceLod) reuse this result
+/// until their next solve.
+#[derive(Component, Reflect, Clone, Default, PartialEq, Debug)]
+#[reflect(Component, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvoidanceResult {
+    /// The velocity the agent wanted, heading straight to its goal.
+    pub preferred_velocity: Vec2,
+
+    /// The velocity chosen by the solver.
+    pub velocity: Vec2,
+
+    /// The avoidance tick on which this result was computed.
+    pub solved_tick: u32,
+}
+
+/// The neighbourhood an agent fed to the solver during its last avoidance solve.
+///
+/// Only recorded for agents that have this component, which the
+/// [`DodgyDebugPlugin`](crate::debug::DodgyDebugPlugin) inserts when needed.
+#[derive(Component, Clone, Default, PartialEq, Debug)]
+pub struct AvoidanceNeighbours {
+    /// The radius of the circle in which neighbours were searched.
+    pub query_radius: f32,
+
+    /// The distance within which obstacle edges were considered.
+    pub obstacle_query_radius: f32,
+
+    /// The neighbouring agents the velocity was computed against.
+    pub agents: Vec<Entity>,
+
+    /// The obstacles the velocity was computed against.
+    pub obstacles: Vec<Entity>,
+}
+
+#[derive(Component, Reflect, Clone, PartialEq, Debug, Deref, DerefMut)]
+#[reflect(Component, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvoidanceOptionsComponent(
+    #[reflect(remote = AvoidanceOptionsReflect)]
+    #[cfg_attr(feature = "serde", serde(with = "AvoidanceOptionsDef"))]
+    pub AvoidanceOptions,
+);
+
+/// Reflection wrapper for dodgy's [`AvoidanceOptions`], which doesn't implement `Reflect`.
+#[reflect_remote(AvoidanceOptions)]
+#[derive(Clone, PartialEq, Debug)]
+#[reflect(PartialEq, Debug)]
+pub struct AvoidanceOptionsReflect {
+    pub obstacle_margin: f32,
+    pub time_horizon: f32,
+    pub obstacle_time_horizon: f32,
+}
+
+/// Serde definition of dodgy's [`AvoidanceOptions`].
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(remote = "AvoidanceOptions")]
+struct AvoidanceOptionsDef {
+    obstacle_margin: f32,
+    time_horizon: f32,
+    obstacle_time_horizon: f32,
+}
 
 impl AvoidanceOptionsComponent {
     pub fn new(
@@ -65,15 +231,43 @@ impl AvoidanceOptionsComponent {
             obstacle_time_horizon,
         })
     }
+
+    pub fn with_obstacle_margin(mut self, obstacle_margin: f32) -> AvoidanceOptionsComponent {
+        self.obstacle_margin = obstacle_margin;
+        self
+    }
+
+    pub fn with_time_horizon(mut self, time_horizon: f32) -> AvoidanceOptionsComponent {
+        self.time_horizon = time_horizon;
+        self
+    }
+
+    pub fn with_obstacle_time_horizon(
+        mut self,
+        obstacle_time_horizon: f32,
+    ) -> AvoidanceOptionsComponent {
+        self.obstacle_time_horizon = obstacle_time_horizon;
+        self
+    }
+}
+
+impl Default for AvoidanceOptionsComponent {
+    fn default() -> Self {
+        AvoidanceOptionsComponent::new(0.1, 3.0, 1.0)
+    }
 }
 
 impl From<&AgentQueryDataMutReadOnlyItem<'_>> for Agent {
     fn from(value: &AgentQueryDataMutReadOnlyItem) -> Self {
+        let responsibility_factor = value
+            .recovery
+            .map_or(1.0, |recovery| recovery.responsibility_factor);
+
         Self {
             position: value.transform.translation.xy(),
             velocity: value.linvel.0,
             radius: value.info.radius,
-            avoidance_responsibility: value.info.avoidance_responsibility,
+            avoidance_responsibility: value.info.avoidance_responsibility * responsibility_factor,
         }
     }
 }
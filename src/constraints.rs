@@ -0,0 +1,52 @@
+use bevy::prelude::{Component, Vec2};
+use dodgy_2d::Line;
+
+/// The velocity-space constraints an agent was solved against.
+///
+/// These are the ORCA lines dodgy fed to its linear program, recorded only for agents that
+/// have this component, which the [`DodgyDebugPlugin`](crate::debug::DodgyDebugPlugin)
+/// inserts on selected agents.
+#[derive(Component, Clone, Default, PartialEq, Debug)]
+pub struct AvoidanceConstraints {
+    /// The half-planes induced by the nearby obstacles, which the solver never relaxes.
+    pub obstacle_lines: Vec<OrcaLine>,
+
+    /// The half-planes induced by the neighbouring agents, which the solver relaxes
+    /// together when they can't all be met.
+    pub agent_lines: Vec<OrcaLine>,
+}
+
+impl AvoidanceConstraints {
+    /// Splits the lines of a solve, where the obstacle lines come first.
+    pub(crate) fn from_solver_lines(lines: Vec<Line>, obstacle_line_count: usize) -> Self {
+        let mut lines = lines.into_iter().map(OrcaLine::from);
+        AvoidanceConstraints {
+            obstacle_lines: lines.by_ref().take(obstacle_line_count).collect(),
+            agent_lines: lines.collect(),
+        }
+    }
+}
+
+/// An ORCA half-plane. Permitted velocities lie on the left of the line going through
+/// [`point`](Self::point) along [`direction`](Self::direction).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OrcaLine {
+    pub point: Vec2,
+    pub direction: Vec2,
+}
+
+impl OrcaLine {
+    /// Whether `velocity` satisfies the constraint.
+    pub fn permits(&self, velocity: Vec2) -> bool {
+        self.direction.perp_dot(velocity - self.point) >= 0.0
+    }
+}
+
+impl From<Line> for OrcaLine {
+    fn from(line: Line) -> Self {
+        OrcaLine {
+            point: line.point,
+            direction: line.direction,
+        }
+    }
+}
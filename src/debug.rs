@@ -1,21 +1,40 @@
-use crate::obstacles::{AsObstacle, TransformObstacle};
+use crate::agents::{AgentGoal, AgentInfo, AvoidanceNeighbours, AvoidanceResult};
+use crate::constraints::AvoidanceConstraints;
+use crate::obstacles::{AsObstacle, DodgyObstacleIndex, ObstacleConversion, TransformObstacle};
 use avian2d::prelude::*;
 use bevy::app::{App, Plugin};
-use bevy::color::palettes::basic::BLUE;
-use bevy::color::palettes::css::PURPLE;
+use bevy::color::palettes::basic::{BLUE, LIME, RED};
+use bevy::color::palettes::css::{ORANGE, PURPLE};
+use bevy::ecs::entity::EntityHashSet;
 use bevy::prelude::*;
 use dodgy_2d::Obstacle;
-use crate::agents::AgentGoal;
 
 pub struct DodgyDebugPlugin;
 
 impl Plugin for DodgyDebugPlugin {
     fn build(&self, app: &mut App) {
-        app.init_gizmo_group::<DodgyDebugGizmos>()
-            .add_systems(Startup, setup_debug_gizmos)
+        app.register_type::<DodgyDebugSelected>()
+            .register_type::<DodgyDebugConfig>()
+            .init_resource::<DodgyDebugConfig>()
+            .init_gizmo_group::<DodgyDebugGizmos>()
+            .add_systems(
+                PreUpdate,
+                (
+                    toggle_debug_config,
+                    sync_debug_gizmos,
+                    (track_agent_neighbours, track_selected_constraints),
+                )
+                    .chain(),
+            )
             .add_systems(
                 PostUpdate,
-                (display_dodgy_obstacles, display_agent_velocity),
+                (
+                    display_dodgy_obstacles.run_if(overlay_enabled(|config| config.obstacles)),
+                    display_agent_velocity.run_if(overlay_enabled(|config| config.velocities)),
+                    display_agent_goal.run_if(overlay_enabled(|config| config.goals)),
+                    display_agent_neighbours.run_if(overlay_enabled(|config| config.neighbours)),
+                    display_velocity_space.run_if(overlay_enabled(|config| config.constraints)),
+                ),
             );
     }
 }
@@ -23,57 +42,422 @@ impl Plugin for DodgyDebugPlugin {
 #[derive(Default, Reflect, GizmoConfigGroup)]
 pub struct DodgyDebugGizmos {}
 
-fn setup_debug_gizmos(mut config_store: ResMut<GizmoConfigStore>) {
-    let (config, _) = config_store.config_mut::<DodgyDebugGizmos>();
-    config.line_style = GizmoLineStyle::Dotted;
+/// Marks the agents that get the detailed debug overlays.
+///
+/// The constraint overlay is only drawn for selected agents, and the other per-agent
+/// overlays are restricted to them as well when [`DodgyDebugConfig::selected_only`]
+/// is set.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default, Debug)]
+pub struct DodgyDebugSelected;
+
+/// Controls what the [`DodgyDebugPlugin`] draws and how.
+#[derive(Resource, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+pub struct DodgyDebugConfig {
+    /// Whether any overlay is drawn.
+    pub enabled: bool,
+
+    /// The key toggling [`enabled`](Self::enabled), if any.
+    pub toggle_key: Option<KeyCode>,
+
+    /// The line style of every overlay.
+    pub line_style: GizmoLineStyle,
+
+    /// Draws the obstacles, highlighting the ones considered during the last solve of the
+    /// shown agents.
+    pub obstacles: bool,
+
+    /// Draws the preferred, avoidance and actual velocity of each agent.
+    pub velocities: bool,
+
+    /// Draws a line to the goal of each agent and its arrival tolerance.
+    pub goals: bool,
+
+    /// Draws the query circle of each agent and lines to the neighbours its velocity
+    /// was computed against.
+    pub neighbours: bool,
+
+    /// Draws a velocity-space inset next to each [`DodgyDebugSelected`] agent, showing
+    /// its ORCA half-planes, obstacle velocity obstacles, preferred and chosen velocity.
+    pub constraints: bool,
+
+    /// Restricts the velocity, goal and neighbour overlays to [`DodgyDebugSelected`] agents.
+    pub selected_only: bool,
+
+    /// The offset of the velocity-space inset from its agent, in world units.
+    pub velocity_space_offset: Vec2,
+
+    /// The world size of one unit of velocity in the velocity-space inset.
+    pub velocity_space_scale: f32,
+
+    pub colors: DodgyDebugColors,
+}
+
+impl Default for DodgyDebugConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            toggle_key: Some(KeyCode::F3),
+            line_style: GizmoLineStyle::Dotted,
+            obstacles: true,
+            velocities: true,
+            goals: true,
+            neighbours: true,
+            constraints: true,
+            selected_only: false,
+            velocity_space_offset: Vec2::new(80.0, 80.0),
+            velocity_space_scale: 1.5,
+            colors: DodgyDebugColors::default(),
+        }
+    }
+}
+
+impl DodgyDebugConfig {
+    /// Whether the per-agent overlays of an agent should be drawn.
+    fn shows_agent(&self, selected: bool) -> bool {
+        selected || !self.selected_only
+    }
+}
+
+/// The colours used by the [`DodgyDebugPlugin`] overlays.
+#[derive(Reflect, Clone, PartialEq, Debug)]
+#[reflect(Default, PartialEq, Debug)]
+pub struct DodgyDebugColors {
+    pub closed_obstacle: Color,
+    pub open_obstacle: Color,
+    pub considered_obstacle: Color,
+    pub preferred_velocity: Color,
+    pub avoidance_velocity: Color,
+    pub actual_velocity: Color,
+    pub goal_line: Color,
+    pub goal_reached: Color,
+    pub goal_pending: Color,
+    pub query_radius: Color,
+    pub neighbour_line: Color,
+    pub velocity_space_frame: Color,
+    pub orca_line: Color,
+    pub obstacle_line: Color,
+}
+
+impl Default for DodgyDebugColors {
+    fn default() -> Self {
+        Self {
+            closed_obstacle: Srgba::hex("#9F2B68").unwrap().into(),
+            open_obstacle: Srgba::hex("#301934").unwrap().into(),
+            considered_obstacle: ORANGE.into(),
+            preferred_velocity: PURPLE.into(),
+            avoidance_velocity: BLUE.into(),
+            actual_velocity: LIME.into(),
+            goal_line: Srgba::hex("#7a6c8f").unwrap().into(),
+            goal_reached: LIME.into(),
+            goal_pending: RED.into(),
+            query_radius: Srgba::hex("#5b6f8a").unwrap().into(),
+            neighbour_line: Srgba::hex("#3fa37c").unwrap().into(),
+            velocity_space_frame: Srgba::hex("#3b4350").unwrap().into(),
+            orca_line: Srgba::hex("#d1495b").unwrap().into(),
+            obstacle_line: Srgba::hex("#edae49").unwrap().into(),
+        }
+    }
+}
+
+fn overlay_enabled(
+    overlay: impl Fn(&DodgyDebugConfig) -> bool,
+) -> impl FnMut(Res<DodgyDebugConfig>) -> bool {
+    move |config: Res<DodgyDebugConfig>| config.enabled && overlay(&config)
+}
+
+fn toggle_debug_config(
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mut config: ResMut<DodgyDebugConfig>,
+) {
+    let (Some(keys), Some(toggle_key)) = (keys, config.toggle_key) else {
+        return;
+    };
+
+    if keys.just_pressed(toggle_key) {
+        config.enabled = !config.enabled;
+    }
+}
+
+fn sync_debug_gizmos(config: Res<DodgyDebugConfig>, mut config_store: ResMut<GizmoConfigStore>) {
+    if !config.is_changed() {
+        return;
+    }
+
+    let (gizmo_config, _) = config_store.config_mut::<DodgyDebugGizmos>();
+    gizmo_config.enabled = config.enabled;
+    gizmo_config.line_style = config.line_style;
+}
+
+/// Makes the avoidance record the neighbourhood of the shown agents while their neighbours
+/// or considered obstacles are displayed, and stop recording for the others.
+///
+/// With [`DodgyDebugConfig::selected_only`] set, only the obstacles considered by the
+/// selected agents are highlighted.
+fn track_agent_neighbours(
+    mut commands: Commands,
+    query: Query<(Entity, Has<AvoidanceNeighbours>, Has<DodgyDebugSelected>), With<AgentInfo>>,
+    config: Res<DodgyDebugConfig>,
+) {
+    let records = config.enabled && (config.neighbours || config.obstacles);
+
+    for (entity, has_neighbours, selected) in query.iter() {
+        let wanted = records && config.shows_agent(selected);
+        if wanted && !has_neighbours {
+            commands
+                .entity(entity)
+                .insert(AvoidanceNeighbours::default());
+        } else if !wanted && has_neighbours {
+            commands.entity(entity).remove::<AvoidanceNeighbours>();
+        }
+    }
+}
+
+/// Makes the avoidance record the constraints of selected agents.
+fn track_selected_constraints(
+    mut commands: Commands,
+    selected: Query<Entity, (With<DodgyDebugSelected>, Without<AvoidanceConstraints>)>,
+    mut deselected: RemovedComponents<DodgyDebugSelected>,
+) {
+    for entity in selected.iter() {
+        commands
+            .entity(entity)
+            .insert(AvoidanceConstraints::default());
+    }
+
+    for entity in deselected.read() {
+        if let Some(mut entity_commands) = commands.get_entity(entity) {
+            entity_commands.remove::<AvoidanceConstraints>();
+        }
+    }
 }
 
 fn display_dodgy_obstacles(
-    query: Query<(&Transform, &RigidBody, &Collider)>,
+    query: Query<(Entity, &Transform, &RigidBody, &Collider)>,
+    index: Res<DodgyObstacleIndex>,
+    conversion: Res<ObstacleConversion>,
+    neighbourhoods: Query<&AvoidanceNeighbours>,
+    config: Res<DodgyDebugConfig>,
     mut gizmos: Gizmos<DodgyDebugGizmos>,
 ) {
-    for (tf, body, collider) in query.iter() {
-        if body.is_dynamic() || body.is_kinematic() {
+    let considered: EntityHashSet = neighbourhoods
+        .iter()
+        .flat_map(|neighbours| neighbours.obstacles.iter().copied())
+        .collect();
+
+    for (entity, tf, body, collider) in query.iter() {
+        if body.is_dynamic() {
             continue;
         }
 
-        if let Some(mut obstacle) = collider.to_obstacle() {
+        for mut obstacle in collider.to_obstacles(&conversion) {
             obstacle.transform_points(tf);
+            draw_obstacle(
+                &mut gizmos,
+                &config,
+                &obstacle,
+                considered.contains(&entity),
+            );
+        }
+    }
 
-            match obstacle {
-                Obstacle::Closed { vertices } => {
-                    let mut vertices_3d: Vec<Vec3> =
-                        vertices.iter().map(|v| Vec3::new(v.x, 1., v.y)).collect();
-
-                    if !vertices_3d.is_empty() {
-                        vertices_3d.push(vertices_3d[0]); // Adds a line to close the shape
-                    }
+    for (entity, entry) in index.iter() {
+        draw_obstacle(
+            &mut gizmos,
+            &config,
+            &entry.obstacle,
+            considered.contains(&entity),
+        );
+    }
+}
 
-                    gizmos.linestrip(vertices_3d, Srgba::hex("#9F2B68").unwrap());
-                }
-                Obstacle::Open { vertices } => {
-                    let vertices_3d: Vec<Vec3> =
-                        vertices.iter().map(|v| Vec3::new(v.x, 1., v.y)).collect();
+fn draw_obstacle(
+    gizmos: &mut Gizmos<DodgyDebugGizmos>,
+    config: &DodgyDebugConfig,
+    obstacle: &Obstacle,
+    is_considered: bool,
+) {
+    match obstacle {
+        Obstacle::Closed { vertices } => {
+            let mut vertices_3d: Vec<Vec3> = vertices.iter().map(|v| v.extend(0.0)).collect();
 
-                    gizmos.linestrip(vertices_3d, Srgba::hex("#301934").unwrap());
-                }
+            if !vertices_3d.is_empty() {
+                vertices_3d.push(vertices_3d[0]); // Adds a line to close the shape
             }
+
+            let color = if is_considered {
+                config.colors.considered_obstacle
+            } else {
+                config.colors.closed_obstacle
+            };
+            gizmos.linestrip(vertices_3d, color);
+        }
+        Obstacle::Open { vertices } => {
+            let vertices_3d: Vec<Vec3> = vertices.iter().map(|v| v.extend(0.0)).collect();
+
+            let color = if is_considered {
+                config.colors.considered_obstacle
+            } else {
+                config.colors.open_obstacle
+            };
+            gizmos.linestrip(vertices_3d, color);
+        }
+    }
+}
+
+fn display_agent_velocity(
+    query: Query<(
+        &Transform,
+        &LinearVelocity,
+        &AvoidanceResult,
+        Has<DodgyDebugSelected>,
+    )>,
+    config: Res<DodgyDebugConfig>,
+    mut gizmos: Gizmos<DodgyDebugGizmos>,
+) {
+    for (tf, linvel, result, selected) in query.iter() {
+        if !config.shows_agent(selected) {
+            continue;
+        }
+        let position = tf.translation.xy();
+
+        gizmos.line_2d(
+            position,
+            position + result.preferred_velocity,
+            config.colors.preferred_velocity,
+        );
+        gizmos.line_2d(
+            position,
+            position + result.velocity,
+            config.colors.avoidance_velocity,
+        );
+        gizmos.line_2d(position, position + linvel.0, config.colors.actual_velocity);
+    }
+}
+
+fn display_agent_goal(
+    query: Query<(&Transform, &AgentGoal, Has<DodgyDebugSelected>)>,
+    config: Res<DodgyDebugConfig>,
+    mut gizmos: Gizmos<DodgyDebugGizmos>,
+) {
+    for (tf, goal, selected) in query.iter() {
+        if !config.shows_agent(selected) {
+            continue;
+        }
+        let position = tf.translation.xy();
+
+        let arrived = position.distance(goal.dest) <= goal.tolerance;
+        let color = if arrived {
+            config.colors.goal_reached
+        } else {
+            config.colors.goal_pending
+        };
+
+        gizmos.line_2d(position, goal.dest, config.colors.goal_line);
+        gizmos.circle_2d(goal.dest, goal.tolerance, color);
+    }
+}
+
+fn display_agent_neighbours(
+    query: Query<(&Transform, &AvoidanceNeighbours, Has<DodgyDebugSelected>)>,
+    transforms: Query<&Transform>,
+    config: Res<DodgyDebugConfig>,
+    mut gizmos: Gizmos<DodgyDebugGizmos>,
+) {
+    for (tf, neighbours, selected) in query.iter() {
+        if !config.shows_agent(selected) {
+            continue;
+        }
+        let position = tf.translation.xy();
+
+        gizmos.circle_2d(
+            position,
+            neighbours.query_radius,
+            config.colors.query_radius,
+        );
+        if neighbours.obstacle_query_radius > 0.0
+            && neighbours.obstacle_query_radius != neighbours.query_radius
+        {
+            gizmos.circle_2d(
+                position,
+                neighbours.obstacle_query_radius,
+                config.colors.query_radius,
+            );
+        }
+
+        for neighbour_tf in transforms.iter_many(&neighbours.agents) {
+            gizmos.line_2d(
+                position,
+                neighbour_tf.translation.xy(),
+                config.colors.neighbour_line,
+            );
         }
     }
 }
 
-fn display_agent_velocity(query: Query<(&Transform, &LinearVelocity, &AgentGoal)>, mut gizmos: Gizmos) {
-    for (tf, linvel, goal) in query.iter() {
-        gizmos.line(
-            tf.translation,
-            tf.translation + linvel.0.extend(0.0),
-            BLUE,
+fn display_velocity_space(
+    query: Query<
+        (
+            &Transform,
+            &AgentInfo,
+            &AvoidanceResult,
+            &AvoidanceConstraints,
+        ),
+        With<DodgyDebugSelected>,
+    >,
+    config: Res<DodgyDebugConfig>,
+    mut gizmos: Gizmos<DodgyDebugGizmos>,
+) {
+    let offset = config.velocity_space_offset;
+    let scale = config.velocity_space_scale;
+    let colors = &config.colors;
+
+    for (tf, info, result, constraints) in query.iter() {
+        let origin = tf.translation.xy() + offset;
+        let extent = info.max_speed * scale;
+        let to_world = |velocity: Vec2| origin + velocity * scale;
+
+        // Frame of the inset: the max speed circle and the velocity axes.
+        gizmos.circle_2d(origin, extent, colors.velocity_space_frame);
+        gizmos.line_2d(
+            origin - Vec2::X * extent,
+            origin + Vec2::X * extent,
+            colors.velocity_space_frame,
+        );
+        gizmos.line_2d(
+            origin - Vec2::Y * extent,
+            origin + Vec2::Y * extent,
+            colors.velocity_space_frame,
         );
 
-        gizmos.line(
-          tf.translation,
-          tf.translation + goal.dest.extend(0.0),
-          PURPLE,
+        let lines = constraints
+            .obstacle_lines
+            .iter()
+            .map(|line| (line, colors.obstacle_line))
+            .chain(
+                constraints
+                    .agent_lines
+                    .iter()
+                    .map(|line| (line, colors.orca_line)),
+            );
+        for (line, color) in lines {
+            let direction = line.direction.normalize_or_zero();
+            let center = to_world(line.point);
+            gizmos.line_2d(
+                center - direction * extent,
+                center + direction * extent,
+                color,
+            );
+            // Tick towards the permitted side of the half-plane.
+            gizmos.line_2d(center, center + direction.perp() * extent * 0.1, color);
+        }
+
+        gizmos.arrow_2d(
+            origin,
+            to_world(result.preferred_velocity),
+            colors.preferred_velocity,
         );
+        gizmos.arrow_2d(origin, to_world(result.velocity), colors.avoidance_velocity);
     }
 }
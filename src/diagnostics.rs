@@ -0,0 +1,134 @@
+use crate::agents::{AgentGoal, AgentInfo, AvoidanceResult};
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, Diagnostics, RegisterDiagnostic};
+use bevy::prelude::*;
+use std::time::Duration;
+
+/// Figures collected by the avoidance passes of the current frame.
+///
+/// In [`AvoidanceMode::Deterministic`](crate::AvoidanceMode) the pass may run several
+/// times per frame, or not at all, in which case these add up over all of them. They are
+/// cleared in `First`.
+#[derive(Resource, Clone, Default, PartialEq, Debug)]
+pub struct AvoidanceStats {
+    /// The number of avoidance passes run this frame.
+    pub passes: usize,
+
+    /// The number of agents whose velocity was solved.
+    pub solved_agents: usize,
+
+    /// The number of neighbours summed over the solved agents.
+    pub total_neighbours: usize,
+
+    /// The largest number of neighbours of a single solved agent.
+    pub max_neighbours: usize,
+
+    /// The number of obstacles summed over the solved agents.
+    pub total_obstacles: usize,
+
+    /// The time spent in the avoidance passes.
+    pub solve_time: Duration,
+}
+
+impl AvoidanceStats {
+    pub(crate) fn record_agent(&mut self, neighbours: usize, obstacles: usize) {
+        self.solved_agents += 1;
+        self.total_neighbours += neighbours;
+        self.max_neighbours = self.max_neighbours.max(neighbours);
+        self.total_obstacles += obstacles;
+    }
+}
+
+pub(crate) fn clear_avoidance_stats(mut stats: ResMut<AvoidanceStats>) {
+    *stats = AvoidanceStats::default();
+}
+
+/// Adds diagnostics about the avoidance to an App, to be read with e.g. `LogDiagnosticsPlugin`.
+///
+/// Requires the [`DodgyPlugin`](crate::DodgyPlugin).
+pub struct DodgyDiagnosticsPlugin {
+    /// An agent is counted as deviating when its chosen velocity is further than this
+    /// fraction of its max speed from its preferred velocity.
+    pub deviation_ratio: f32,
+}
+
+impl Default for DodgyDiagnosticsPlugin {
+    fn default() -> Self {
+        Self {
+            deviation_ratio: 0.5,
+        }
+    }
+}
+
+impl Plugin for DodgyDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(DeviationRatio(self.deviation_ratio))
+            .register_diagnostic(Diagnostic::new(Self::AGENT_COUNT))
+            .register_diagnostic(Diagnostic::new(Self::PASSES))
+            .register_diagnostic(Diagnostic::new(Self::AVERAGE_NEIGHBOURS))
+            .register_diagnostic(Diagnostic::new(Self::MAX_NEIGHBOURS))
+            .register_diagnostic(Diagnostic::new(Self::AVERAGE_OBSTACLES))
+            .register_diagnostic(Diagnostic::new(Self::SOLVE_TIME).with_suffix("ms"))
+            .register_diagnostic(Diagnostic::new(Self::AGENTS_AT_GOAL))
+            .register_diagnostic(Diagnostic::new(Self::DEVIATING_AGENTS))
+            .add_systems(PostUpdate, Self::diagnostic_system);
+    }
+}
+
+impl DodgyDiagnosticsPlugin {
+    pub const AGENT_COUNT: DiagnosticPath = DiagnosticPath::const_new("dodgy/agent_count");
+    pub const PASSES: DiagnosticPath = DiagnosticPath::const_new("dodgy/passes");
+    pub const AVERAGE_NEIGHBOURS: DiagnosticPath =
+        DiagnosticPath::const_new("dodgy/average_neighbours");
+    pub const MAX_NEIGHBOURS: DiagnosticPath = DiagnosticPath::const_new("dodgy/max_neighbours");
+    pub const AVERAGE_OBSTACLES: DiagnosticPath =
+        DiagnosticPath::const_new("dodgy/average_obstacles");
+    pub const SOLVE_TIME: DiagnosticPath = DiagnosticPath::const_new("dodgy/solve_time");
+    pub const AGENTS_AT_GOAL: DiagnosticPath = DiagnosticPath::const_new("dodgy/agents_at_goal");
+    pub const DEVIATING_AGENTS: DiagnosticPath =
+        DiagnosticPath::const_new("dodgy/deviating_agents");
+
+    fn diagnostic_system(
+        mut diagnostics: Diagnostics,
+        agents: Query<(&Transform, &AgentInfo, &AvoidanceResult, Option<&AgentGoal>)>,
+        stats: Res<AvoidanceStats>,
+        deviation_ratio: Res<DeviationRatio>,
+    ) {
+        let mut agent_count = 0;
+        let mut agents_at_goal = 0;
+        let mut deviating_agents = 0;
+        for (tf, info, result, goal) in agents.iter() {
+            agent_count += 1;
+
+            if goal.is_some_and(|goal| tf.translation.xy().distance(goal.dest) <= goal.tolerance) {
+                agents_at_goal += 1;
+            }
+
+            let deviation = result.velocity.distance(result.preferred_velocity);
+            if deviation > deviation_ratio.0 * info.max_speed {
+                deviating_agents += 1;
+            }
+        }
+
+        diagnostics.add_measurement(&Self::AGENT_COUNT, || agent_count as f64);
+        diagnostics.add_measurement(&Self::PASSES, || stats.passes as f64);
+        diagnostics.add_measurement(&Self::AGENTS_AT_GOAL, || agents_at_goal as f64);
+        diagnostics.add_measurement(&Self::DEVIATING_AGENTS, || deviating_agents as f64);
+        diagnostics.add_measurement(&Self::MAX_NEIGHBOURS, || stats.max_neighbours as f64);
+        diagnostics.add_measurement(&Self::SOLVE_TIME, || {
+            stats.solve_time.as_secs_f64() * 1000.0
+        });
+
+        if stats.solved_agents > 0 {
+            let solved_agents = stats.solved_agents as f64;
+            diagnostics.add_measurement(&Self::AVERAGE_NEIGHBOURS, || {
+                stats.total_neighbours as f64 / solved_agents
+            });
+            diagnostics.add_measurement(&Self::AVERAGE_OBSTACLES, || {
+                stats.total_obstacles as f64 / solved_agents
+            });
+        }
+    }
+}
+
+#[derive(Resource)]
+struct DeviationRatio(f32);
@@ -0,0 +1,92 @@
+use bevy::prelude::*;
+use dodgy_2d::Agent;
+
+/// Blends boids-style steering into the preferred velocity of an agent before the
+/// avoidance solve, so herds and swarms move as a cohesive group while the solver still
+/// keeps them from overlapping.
+///
+/// Flockmates are the neighbouring agents of the same
+/// [`AvoidanceGroup`](crate::groups::AvoidanceGroup), or without a group if the agent has
+/// none, within [`radius`](Self::radius). They are taken from the avoidance neighbour
+/// query, so a radius larger than the avoidance query radius,
+/// `radius + time_horizon * max_speed`, has no further effect.
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Debug)]
+#[reflect(Component, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Flocking {
+    /// The weight of steering away from close flockmates.
+    pub separation: f32,
+
+    /// The weight of matching the average velocity of the flockmates.
+    pub alignment: f32,
+
+    /// The weight of steering toward the centre of the flockmates.
+    pub cohesion: f32,
+
+    /// The distance within which neighbours count as flockmates.
+    pub radius: f32,
+}
+
+impl Default for Flocking {
+    fn default() -> Self {
+        Flocking::new(1.0, 0.5, 0.5, 5.0)
+    }
+}
+
+impl Flocking {
+    pub fn new(separation: f32, alignment: f32, cohesion: f32, radius: f32) -> Flocking {
+        Flocking {
+            separation,
+            alignment,
+            cohesion,
+            radius,
+        }
+    }
+
+    /// Adds the flocking steering toward `flockmates` to `preferred_velocity`, keeping
+    /// the result within `max_speed`.
+    ///
+    /// Each rule contributes a vector of at most unit length, scaled by its weight and by
+    /// `max_speed`.
+    pub fn blend_preferred_velocity(
+        &self,
+        agent: &Agent,
+        flockmates: &[&Agent],
+        preferred_velocity: Vec2,
+        max_speed: f32,
+    ) -> Vec2 {
+        let flockmates: Vec<&Agent> = flockmates
+            .iter()
+            .copied()
+            .filter(|mate| mate.position.distance(agent.position) <= self.radius)
+            .collect();
+        if flockmates.is_empty() || self.radius <= 0.0 {
+            return preferred_velocity;
+        }
+        let count = flockmates.len() as f32;
+
+        // Pushes away from each mate, harder the closer it is.
+        let separation = flockmates
+            .iter()
+            .map(|mate| {
+                let away = agent.position - mate.position;
+                away.normalize_or_zero() * (1.0 - away.length() / self.radius)
+            })
+            .sum::<Vec2>()
+            .clamp_length_max(1.0);
+
+        let average_velocity = flockmates.iter().map(|mate| mate.velocity).sum::<Vec2>() / count;
+        let alignment = if max_speed > 0.0 {
+            (average_velocity / max_speed).clamp_length_max(1.0)
+        } else {
+            Vec2::ZERO
+        };
+
+        let centre = flockmates.iter().map(|mate| mate.position).sum::<Vec2>() / count;
+        let cohesion = ((centre - agent.position) / self.radius).clamp_length_max(1.0);
+
+        let steering =
+            separation * self.separation + alignment * self.alignment + cohesion * self.cohesion;
+        (preferred_velocity + steering * max_speed).clamp_length_max(max_speed)
+    }
+}
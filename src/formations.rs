@@ -0,0 +1,178 @@
+use crate::agents::{AgentGoal, AgentInfo};
+use bevy::ecs::entity::{EntityMapper, MapEntities};
+use bevy::ecs::reflect::ReflectMapEntities;
+use bevy::math::Vec3Swizzles;
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+
+/// A squad of agents moving together towards the [`AgentGoal`] of the formation entity.
+///
+/// The formation's [`Transform`] is its anchor: it travels towards the goal at
+/// [`speed`](Self::speed) and turns to face its direction of travel. Every member is given
+/// its slot, rotated by the heading, as its own [`AgentGoal`], so members keep running
+/// avoidance against each other and outsiders. Slots are assigned in the order of
+/// [`members`](Self::members), and despawned members are removed so the others close ranks.
+#[derive(Component, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Component, MapEntities, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[require(Transform)]
+pub struct Formation {
+    pub shape: FormationShape,
+
+    /// The distance between neighbouring slots.
+    pub spacing: f32,
+
+    /// The speed at which the anchor travels. It should stay below the slowest member's
+    /// max speed so the members can keep up.
+    pub speed: f32,
+
+    /// The tolerance of the goals given to the members.
+    pub slot_tolerance: f32,
+
+    /// The agents of the formation, in slot order.
+    pub members: Vec<Entity>,
+}
+
+impl Formation {
+    pub fn new(shape: FormationShape, spacing: f32, speed: f32) -> Formation {
+        Formation {
+            shape,
+            spacing,
+            speed,
+            slot_tolerance: spacing * 0.25,
+            members: Vec::new(),
+        }
+    }
+
+    pub fn with_slot_tolerance(mut self, slot_tolerance: f32) -> Formation {
+        self.slot_tolerance = slot_tolerance;
+        self
+    }
+
+    pub fn with_members(mut self, members: impl IntoIterator<Item = Entity>) -> Formation {
+        self.members.extend(members);
+        self
+    }
+
+    /// The offsets of the slots from the anchor, with the formation facing `+X`.
+    pub fn slots(&self) -> Vec<Vec2> {
+        self.shape.slots(self.members.len(), self.spacing)
+    }
+}
+
+impl MapEntities for Formation {
+    fn map_entities<M: EntityMapper>(&mut self, entity_mapper: &mut M) {
+        for member in &mut self.members {
+            *member = entity_mapper.map_entity(*member);
+        }
+    }
+}
+
+/// The arrangement of the slots of a [`Formation`].
+#[derive(Reflect, Clone, PartialEq, Debug, Default)]
+#[reflect(Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FormationShape {
+    /// Side by side, perpendicular to the heading.
+    #[default]
+    Line,
+
+    /// The first member leads and the others fall back alternately to its left and right.
+    Wedge,
+
+    /// One behind the other.
+    Column,
+
+    /// Evenly spread on a circle around the anchor.
+    Circle,
+
+    /// Offsets from the anchor with the formation facing `+X`. Members beyond the last
+    /// slot line up behind the formation.
+    Custom(Vec<Vec2>),
+}
+
+impl FormationShape {
+    /// The offsets of `count` slots from the anchor, with the formation facing `+X`.
+    pub fn slots(&self, count: usize, spacing: f32) -> Vec<Vec2> {
+        match self {
+            FormationShape::Line => {
+                let half_width = (count.max(1) - 1) as f32 * 0.5;
+                (0..count)
+                    .map(|i| Vec2::new(0.0, (half_width - i as f32) * spacing))
+                    .collect()
+            }
+            FormationShape::Wedge => (0..count)
+                .map(|i| {
+                    let rank = (i + 1) / 2;
+                    let side = if i % 2 == 1 { 1.0 } else { -1.0 };
+                    Vec2::new(-(rank as f32), side * rank as f32) * spacing
+                })
+                .collect(),
+            FormationShape::Column => (0..count)
+                .map(|i| Vec2::new(-(i as f32) * spacing, 0.0))
+                .collect(),
+            FormationShape::Circle => {
+                if count <= 1 {
+                    return vec![Vec2::ZERO; count];
+                }
+                // Keep neighbouring slots `spacing` apart along the circle.
+                let radius = spacing / (2.0 * (TAU / (2.0 * count as f32)).sin());
+                (0..count)
+                    .map(|i| Vec2::from_angle(TAU * i as f32 / count as f32) * radius)
+                    .collect()
+            }
+            FormationShape::Custom(offsets) => {
+                let rear = offsets.iter().map(|o| o.x).fold(0.0, f32::min);
+                (0..count)
+                    .map(|i| {
+                        offsets.get(i).copied().unwrap_or_else(|| {
+                            let extra = (i - offsets.len() + 1) as f32;
+                            Vec2::new(rear - extra * spacing, 0.0)
+                        })
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// Moves the formations towards their goal and hands the slots out to their members.
+pub fn update_formations(
+    mut commands: Commands,
+    mut formations: Query<(&mut Formation, &mut Transform, Option<&AgentGoal>)>,
+    mut members: Query<Option<&mut AgentGoal>, (With<AgentInfo>, Without<Formation>)>,
+    time: Res<Time>,
+) {
+    for (mut formation, mut transform, goal) in formations.iter_mut() {
+        // Members that were despawned or stopped being agents give up their slot.
+        if formation.members.iter().any(|e| !members.contains(*e)) {
+            formation.members.retain(|e| members.contains(*e));
+        }
+
+        if let Some(goal) = goal {
+            let to_goal = goal.dest - transform.translation.xy();
+            let distance = to_goal.length();
+            if distance > goal.tolerance {
+                let step = (formation.speed * time.delta_secs()).min(distance);
+                let direction = to_goal / distance;
+                transform.translation += (direction * step).extend(0.0);
+                transform.rotation = Quat::from_rotation_z(direction.to_angle());
+            }
+        }
+
+        let anchor = transform.translation.xy();
+        let heading = (transform.rotation * Vec3::X).xy().normalize_or(Vec2::X);
+        for (&member, offset) in formation.members.iter().zip(formation.slots()) {
+            let slot = AgentGoal::new(anchor + heading.rotate(offset), formation.slot_tolerance);
+            match members.get_mut(member) {
+                Ok(Some(mut goal)) => {
+                    goal.set_if_neq(slot);
+                }
+                Ok(None) => {
+                    commands.entity(member).insert(slot);
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
@@ -1,4 +1,9 @@
-use bevy::math::{Vec2, Vec3};
+use bevy::math::{UVec2, Vec2, Vec3};
+use bevy::reflect::Reflect;
+use bevy::utils::HashMap;
+use dodgy_2d::Obstacle;
+use std::f32::consts::{PI, TAU};
+use std::hash::Hash;
 
 pub fn rect_inner(size: Vec3) -> [Vec2; 4] {
     let half_size = size / 2.;
@@ -14,3 +19,592 @@ pub fn point_on_circle(center: (f32, f32), radius: f32, theta: f32) -> Vec2 {
     let y = center.1 + radius * theta.sin();
     Vec2::new(x, y)
 }
+
+/// The edges of an obstacle, including the closing edge of closed obstacles.
+pub fn obstacle_edges(obstacle: &Obstacle) -> Vec<(Vec2, Vec2)> {
+    match obstacle {
+        Obstacle::Closed { vertices } => (0..vertices.len())
+            .map(|i| (vertices[i], vertices[(i + 1) % vertices.len()]))
+            .collect(),
+        Obstacle::Open { vertices } => vertices.windows(2).map(|w| (w[0], w[1])).collect(),
+    }
+}
+
+/// The distance between `point` and the segment from `a` to `b`.
+pub fn distance_to_segment(point: Vec2, a: Vec2, b: Vec2) -> f32 {
+    let ab = b - a;
+    let t = if ab.length_squared() > 0.0 {
+        ((point - a).dot(ab) / ab.length_squared()).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    point.distance(a + t * ab)
+}
+
+/// Twice the signed area of a polygon, positive when its vertices are counter-clockwise.
+pub fn signed_area(vertices: &[Vec2]) -> f32 {
+    (0..vertices.len())
+        .map(|i| vertices[i].perp_dot(vertices[(i + 1) % vertices.len()]))
+        .sum()
+}
+
+/// Chains directed edges into closed loops, following each edge with one that starts
+/// where it ends.
+///
+/// Edges that don't close a loop are dropped. When several edges leave the same vertex,
+/// the one listed first is followed.
+pub fn chain_edges<T: Copy + Eq + Hash>(edges: &[(T, T)]) -> Vec<Vec<T>> {
+    let mut outgoing: HashMap<T, Vec<usize>> = HashMap::default();
+    for (i, (start, _)) in edges.iter().enumerate() {
+        outgoing.entry(*start).or_default().push(i);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut loops = vec![];
+    for first in 0..edges.len() {
+        if used[first] {
+            continue;
+        }
+        used[first] = true;
+        let start = edges[first].0;
+        let mut chain = vec![start];
+        let mut current = edges[first].1;
+        while current != start {
+            let next = outgoing
+                .get(&current)
+                .and_then(|candidates| candidates.iter().copied().find(|i| !used[*i]));
+            let Some(next) = next else {
+                break;
+            };
+            used[next] = true;
+            chain.push(current);
+            current = edges[next].1;
+        }
+        if current == start {
+            loops.push(chain);
+        }
+    }
+    loops
+}
+
+/// Builds obstacle outlines from a boolean occupancy grid, such as the solid tiles of a
+/// tilemap.
+///
+/// Adjacent solid cells are merged and the outlines follow the cell borders with only
+/// their corners as vertices, so a wall of tiles becomes a single obstacle without the
+/// internal seams agents snag on. Outlines run counter-clockwise around solid areas and
+/// clockwise around the holes enclosed in them.
+///
+/// Cell `(x, y)` covers `origin + (x, y) * cell_size` to `origin + (x + 1, y + 1) * cell_size`.
+#[derive(Clone, PartialEq, Debug)]
+pub struct GridObstacleBuilder {
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+    cell_size: Vec2,
+    origin: Vec2,
+    connect_diagonals: bool,
+}
+
+impl GridObstacleBuilder {
+    /// Creates an empty grid.
+    pub fn new(width: usize, height: usize, cell_size: Vec2) -> GridObstacleBuilder {
+        GridObstacleBuilder {
+            width,
+            height,
+            cells: vec![false; width * height],
+            cell_size,
+            origin: Vec2::ZERO,
+            connect_diagonals: false,
+        }
+    }
+
+    /// Creates a grid from its cells, row by row starting from `y = 0`.
+    pub fn from_cells(
+        width: usize,
+        height: usize,
+        cell_size: Vec2,
+        cells: impl IntoIterator<Item = bool>,
+    ) -> GridObstacleBuilder {
+        let mut builder = GridObstacleBuilder::new(width, height, cell_size);
+        for (cell, solid) in builder.cells.iter_mut().zip(cells) {
+            *cell = solid;
+        }
+        builder
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> GridObstacleBuilder {
+        self.origin = origin;
+        self
+    }
+
+    /// Whether solid cells touching only by a corner share an outline. By default they
+    /// get separate outlines and agents may squeeze between them.
+    pub fn with_connected_diagonals(mut self, connect_diagonals: bool) -> GridObstacleBuilder {
+        self.connect_diagonals = connect_diagonals;
+        self
+    }
+
+    /// Marks a cell as solid or free. Cells outside the grid are ignored.
+    pub fn set(&mut self, x: usize, y: usize, solid: bool) -> &mut Self {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = solid;
+        }
+        self
+    }
+
+    /// Whether a cell is solid. Cells outside the grid are free.
+    pub fn is_solid(&self, x: isize, y: isize) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as usize) < self.width
+            && (y as usize) < self.height
+            && self.cells[y as usize * self.width + x as usize]
+    }
+
+    /// Traces the outlines of the solid areas.
+    pub fn build(&self) -> Vec<Obstacle> {
+        // The lattice of cell corners, with the directions of the border edges leaving
+        // each corner: +X, +Y, -X and -Y. Edges keep the solid cells on their left.
+        const STEPS: [(isize, isize); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+        let columns = self.width + 1;
+        let mut outgoing = vec![0u8; columns * (self.height + 1)];
+        for y in 0..=self.height as isize {
+            for x in 0..=self.width as isize {
+                // Marching squares over the four cells around the corner.
+                let bottom_left = self.is_solid(x - 1, y - 1);
+                let bottom_right = self.is_solid(x, y - 1);
+                let top_left = self.is_solid(x - 1, y);
+                let top_right = self.is_solid(x, y);
+                let edges = [
+                    top_right && !bottom_right,
+                    top_left && !top_right,
+                    bottom_left && !top_left,
+                    bottom_right && !bottom_left,
+                ];
+                let index = y as usize * columns + x as usize;
+                for (direction, edge) in edges.into_iter().enumerate() {
+                    if edge {
+                        outgoing[index] |= 1 << direction;
+                    }
+                }
+            }
+        }
+
+        // A corner where two diagonal cells meet has two edges leaving it: turning left
+        // keeps the cells apart, turning right joins them.
+        let corners = outgoing.clone();
+        let turn = if self.connect_diagonals { 3 } else { 1 };
+        let next_direction = |index: usize, direction: usize| match corners[index].count_ones() {
+            2 => (direction + turn) % 4,
+            _ => corners[index].trailing_zeros() as usize,
+        };
+
+        let mut obstacles = vec![];
+        for start in 0..outgoing.len() {
+            while outgoing[start] != 0 {
+                let first_direction = outgoing[start].trailing_zeros() as usize;
+                let mut vertices = vec![];
+                let mut index = start;
+                let mut direction = first_direction;
+                loop {
+                    outgoing[index] &= !(1 << direction);
+                    let (step_x, step_y) = STEPS[direction];
+                    let x = (index % columns) as isize + step_x;
+                    let y = (index / columns) as isize + step_y;
+                    index = y as usize * columns + x as usize;
+
+                    // Only the corners of the outline are kept.
+                    let next = next_direction(index, direction);
+                    if next != direction {
+                        vertices.push(self.origin + Vec2::new(x as f32, y as f32) * self.cell_size);
+                    }
+                    if index == start && next == first_direction {
+                        break;
+                    }
+                    direction = next;
+                }
+                obstacles.push(Obstacle::Closed { vertices });
+            }
+        }
+        obstacles
+    }
+}
+
+/// The outline of a counter-clockwise convex polygon grown by `radius`, with its corners
+/// rounded by arcs of `segments` per full turn.
+///
+/// A single vertex gives a circle and two vertices give a capsule.
+pub fn rounded_convex(vertices: &[Vec2], radius: f32, segments: usize) -> Vec<Vec2> {
+    let step = TAU / segments.max(3) as f32;
+    match vertices {
+        [] => vec![],
+        [center] => (0..segments.max(3))
+            .map(|i| point_on_circle((center.x, center.y), radius, i as f32 * step))
+            .collect(),
+        _ => {
+            let outward = |edge: Vec2| Vec2::new(edge.y, -edge.x).normalize_or_zero();
+            let mut outline = vec![];
+            for (i, vertex) in vertices.iter().enumerate() {
+                let previous = vertices[(i + vertices.len() - 1) % vertices.len()];
+                let next = vertices[(i + 1) % vertices.len()];
+                let start = outward(*vertex - previous).to_angle();
+                let turn = outward(next - *vertex).to_angle() - start;
+                let turn = if turn < 0.0 { turn + TAU } else { turn };
+                let arc_segments = (turn / step).ceil() as usize;
+                outline.extend((0..=arc_segments).map(|j| {
+                    let theta = start + turn * j as f32 / arc_segments.max(1) as f32;
+                    point_on_circle((vertex.x, vertex.y), radius, theta)
+                }));
+            }
+            outline
+        }
+    }
+}
+
+/// Whether a point is inside a polygon, whatever its winding.
+pub fn contains_point(vertices: &[Vec2], point: Vec2) -> bool {
+    let mut inside = false;
+    for i in 0..vertices.len() {
+        let a = vertices[i];
+        let b = vertices[(i + 1) % vertices.len()];
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) / (b.y - a.y) * (b.x - a.x)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Merges overlapping closed obstacles into single outlines by rasterizing them into a
+/// [`GridObstacleBuilder`] of `cell_size`, so the result is accurate to about a cell.
+pub fn union_obstacles(obstacles: &[Obstacle], cell_size: f32) -> Vec<Obstacle> {
+    let polygons: Vec<&Vec<Vec2>> = obstacles
+        .iter()
+        .filter_map(|obstacle| match obstacle {
+            Obstacle::Closed { vertices } if !vertices.is_empty() => Some(vertices),
+            _ => None,
+        })
+        .collect();
+    let Some(min) = polygons
+        .iter()
+        .flat_map(|v| v.iter().copied())
+        .reduce(Vec2::min)
+    else {
+        return vec![];
+    };
+    let max = polygons
+        .iter()
+        .flat_map(|v| v.iter().copied())
+        .fold(min, Vec2::max);
+
+    let size = ((max - min) / cell_size).ceil().as_uvec2().max(UVec2::ONE);
+    let mut builder =
+        GridObstacleBuilder::new(size.x as usize, size.y as usize, Vec2::splat(cell_size))
+            .with_origin(min);
+    for y in 0..size.y as usize {
+        for x in 0..size.x as usize {
+            let center = min + (Vec2::new(x as f32, y as f32) + 0.5) * cell_size;
+            let solid = polygons
+                .iter()
+                .any(|vertices| contains_point(vertices, center));
+            builder.set(x, y, solid);
+        }
+    }
+    builder.build()
+}
+
+/// Merges polygons sharing whole edges, like the triangles of a mesh or the parts of a
+/// convex decomposition, into their outlines.
+///
+/// The polygons must be counter-clockwise. Edges shared by two polygons run in opposite
+/// directions and cancel out, and the remaining edges are chained into outlines,
+/// counter-clockwise around solid areas and clockwise around holes. Vertices are matched
+/// by exact position, and edges overlapping only partially are kept.
+pub fn merge_shared_edges(polygons: &[Vec<Vec2>]) -> Vec<Vec<Vec2>> {
+    let mut positions: Vec<Vec2> = vec![];
+    let mut ids: HashMap<(u32, u32), usize> = HashMap::default();
+    let mut id = |position: Vec2| {
+        *ids.entry((position.x.to_bits(), position.y.to_bits()))
+            .or_insert_with(|| {
+                positions.push(position);
+                positions.len() - 1
+            })
+    };
+
+    let mut edges: Vec<(usize, usize)> = vec![];
+    let mut alive: Vec<bool> = vec![];
+    let mut open: HashMap<(usize, usize), Vec<usize>> = HashMap::default();
+    for polygon in polygons {
+        let polygon: Vec<usize> = polygon.iter().map(|v| id(*v)).collect();
+        for i in 0..polygon.len() {
+            let edge = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            if edge.0 == edge.1 {
+                continue;
+            }
+            match open.get_mut(&(edge.1, edge.0)).and_then(Vec::pop) {
+                Some(twin) => alive[twin] = false,
+                None => {
+                    open.entry(edge).or_default().push(edges.len());
+                    edges.push(edge);
+                    alive.push(true);
+                }
+            }
+        }
+    }
+
+    let boundary: Vec<(usize, usize)> = edges
+        .into_iter()
+        .zip(alive)
+        .filter_map(|(edge, alive)| alive.then_some(edge))
+        .collect();
+    chain_edges(&boundary)
+        .into_iter()
+        .map(|outline| {
+            let outline: Vec<Vec2> = outline.into_iter().map(|i| positions[i]).collect();
+            remove_collinear(&outline)
+        })
+        .filter(|outline| outline.len() >= 3)
+        .collect()
+}
+
+/// Drops the vertices lying on a straight line between their neighbours.
+pub fn remove_collinear(vertices: &[Vec2]) -> Vec<Vec2> {
+    (0..vertices.len())
+        .filter(|&i| {
+            let previous = vertices[(i + vertices.len() - 1) % vertices.len()];
+            let next = vertices[(i + 1) % vertices.len()];
+            let incoming = vertices[i] - previous;
+            let outgoing = next - vertices[i];
+            incoming.perp_dot(outgoing).abs() > 1e-6 * incoming.length() * outgoing.length()
+                || incoming.dot(outgoing) < 0.0
+        })
+        .map(|i| vertices[i])
+        .collect()
+}
+
+/// How the convex corners of an inflated obstacle are shaped.
+#[derive(Reflect, Clone, Copy, PartialEq, Debug)]
+#[reflect(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CornerStyle {
+    /// Arcs around the corners, the exact Minkowski sum with a disk.
+    Round,
+
+    /// The edges are extended until they meet, unless the point would be farther than
+    /// `limit` times the inflation distance, in which case the corner is cut flat.
+    Mitre { limit: f32 },
+}
+
+/// Grows an obstacle by `distance`, turning open obstacles into closed outlines around
+/// them. Arcs use `segments` per full turn.
+///
+/// Closed obstacles grow outward when counter-clockwise, and inward when clockwise, so
+/// the free space shrinks either way. Concave corners are the intersection of the offset
+/// edges, which is exact unless the edges around them are shorter than `distance`.
+pub fn inflate_obstacle(
+    obstacle: &Obstacle,
+    distance: f32,
+    corners: CornerStyle,
+    segments: usize,
+) -> Obstacle {
+    let vertices = match obstacle {
+        Obstacle::Closed { vertices } => vertices.clone(),
+        // Go down the chain and back up, so both sides are offset and the ends capped.
+        Obstacle::Open { vertices } => {
+            let mut around = vertices.clone();
+            around.extend(
+                vertices
+                    .iter()
+                    .rev()
+                    .skip(1)
+                    .take(vertices.len().saturating_sub(2)),
+            );
+            around
+        }
+    };
+    if distance <= 0.0 || vertices.is_empty() {
+        return Obstacle::Closed { vertices };
+    }
+
+    let normal = |edge: Vec2| Vec2::new(edge.y, -edge.x).normalize_or_zero();
+    let step = TAU / segments.max(3) as f32;
+    let count = vertices.len();
+    let mut outline = vec![];
+    for (i, vertex) in vertices.iter().copied().enumerate() {
+        let incoming = vertex - vertices[(i + count - 1) % count];
+        let outgoing = vertices[(i + 1) % count] - vertex;
+        let (before, after) = (normal(incoming), normal(outgoing));
+        if before == Vec2::ZERO || after == Vec2::ZERO {
+            // Skip duplicate vertices.
+            continue;
+        }
+
+        let cross = incoming.perp_dot(outgoing);
+        let reversal = cross == 0.0 && incoming.dot(outgoing) < 0.0;
+        let mitre = (before + after).normalize_or_zero();
+        if cross <= 0.0 && !reversal {
+            // Concave or straight: the offset edges meet on the bisector.
+            outline.push(vertex + mitre * distance / mitre.dot(before).max(1e-3));
+            continue;
+        }
+
+        match corners {
+            CornerStyle::Round => {
+                let turn = if reversal {
+                    PI
+                } else {
+                    before.perp_dot(after).atan2(before.dot(after))
+                };
+                let start = before.to_angle();
+                let arc_segments = (turn / step).ceil().max(1.0) as usize;
+                outline.extend((0..=arc_segments).map(|j| {
+                    let theta = start + turn * j as f32 / arc_segments as f32;
+                    point_on_circle((vertex.x, vertex.y), distance, theta)
+                }));
+            }
+            CornerStyle::Mitre { limit } => {
+                let cos_half = mitre.dot(before);
+                if !reversal && cos_half * limit >= 1.0 {
+                    outline.push(vertex + mitre * distance / cos_half);
+                } else {
+                    outline.push(vertex + before * distance);
+                    outline.push(vertex + after * distance);
+                }
+            }
+        }
+    }
+    Obstacle::Closed { vertices: outline }
+}
+
+/// The convex hull of points, counter-clockwise, without collinear vertices.
+pub fn convex_hull(points: &[Vec2]) -> Vec<Vec2> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    // Andrew's monotone chain: the lower hull left to right, then the upper one back.
+    let turns_left = |hull: &[Vec2], point: Vec2| {
+        let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+        (b - a).perp_dot(point - a) > 0.0
+    };
+    let mut hull: Vec<Vec2> = Vec::with_capacity(points.len() + 1);
+    for point in points.iter() {
+        while hull.len() >= 2 && !turns_left(&hull, *point) {
+            hull.pop();
+        }
+        hull.push(*point);
+    }
+    let lower = hull.len() + 1;
+    for point in points.iter().rev().skip(1) {
+        while hull.len() >= lower && !turns_left(&hull, *point) {
+            hull.pop();
+        }
+        hull.push(*point);
+    }
+    hull.pop();
+    hull
+}
+
+/// Whether a counter-clockwise polygon has no reflex corner.
+pub fn is_convex(vertices: &[Vec2]) -> bool {
+    let n = vertices.len();
+    (0..n).all(|i| {
+        let (previous, vertex, next) = (
+            vertices[(i + n - 1) % n],
+            vertices[i],
+            vertices[(i + 1) % n],
+        );
+        (vertex - previous).perp_dot(next - vertex) >= 0.0
+    })
+}
+
+/// The region covered by an obstacle moving at `linear` and turning at `angular` radians
+/// per second around `center` during `duration`.
+///
+/// The obstacle is placed at `samples` times over the duration besides its current pose.
+/// Solid convex obstacles are swept into the hull of all placements; other obstacles are
+/// returned once per placement.
+pub fn sweep_obstacle(
+    obstacle: &Obstacle,
+    center: Vec2,
+    linear: Vec2,
+    angular: f32,
+    duration: f32,
+    samples: usize,
+) -> Vec<Obstacle> {
+    let samples = samples.max(1);
+    let (vertices, closed) = match obstacle {
+        Obstacle::Closed { vertices } => (vertices, true),
+        Obstacle::Open { vertices } => (vertices, false),
+    };
+    let placements = (0..=samples).map(|k| {
+        let t = duration * k as f32 / samples as f32;
+        let rotation = Vec2::from_angle(angular * t);
+        vertices
+            .iter()
+            .map(|vertex| center + linear * t + rotation.rotate(*vertex - center))
+            .collect::<Vec<_>>()
+    });
+
+    if closed && signed_area(vertices) > 0.0 && is_convex(vertices) {
+        let points: Vec<Vec2> = placements.flatten().collect();
+        return vec![Obstacle::Closed {
+            vertices: convex_hull(&points),
+        }];
+    }
+    placements
+        .map(|vertices| {
+            if closed {
+                Obstacle::Closed { vertices }
+            } else {
+                Obstacle::Open { vertices }
+            }
+        })
+        .collect()
+}
+
+/// The parts of an obstacle whose edges come within `radius` of `center`, or `None` when
+/// all of them do.
+///
+/// A closed obstacle that loses some edges is split into open chains of the kept edges.
+pub fn cull_obstacle(obstacle: &Obstacle, center: Vec2, radius: f32) -> Option<Vec<Obstacle>> {
+    let edges = obstacle_edges(obstacle);
+    let kept: Vec<bool> = edges
+        .iter()
+        .map(|(a, b)| distance_to_segment(center, *a, *b) <= radius)
+        .collect();
+    if kept.iter().all(|kept| *kept) {
+        return None;
+    }
+
+    // Start after a culled edge, so that a chain going around the end of a closed
+    // obstacle isn't cut in two.
+    let start = match obstacle {
+        Obstacle::Closed { .. } => kept.iter().position(|kept| !kept).unwrap_or(0) + 1,
+        Obstacle::Open { .. } => 0,
+    };
+    let mut chains = vec![];
+    let mut chain: Vec<Vec2> = vec![];
+    for i in (start..start + edges.len()).map(|i| i % edges.len()) {
+        let (a, b) = edges[i];
+        if kept[i] {
+            if chain.is_empty() {
+                chain.push(a);
+            }
+            chain.push(b);
+        } else if !chain.is_empty() {
+            chains.push(Obstacle::Open {
+                vertices: std::mem::take(&mut chain),
+            });
+        }
+    }
+    if !chain.is_empty() {
+        chains.push(Obstacle::Open { vertices: chain });
+    }
+    Some(chains)
+}
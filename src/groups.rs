@@ -0,0 +1,106 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use dodgy_2d::Agent;
+
+/// Assigns an agent to a group whose interactions with other groups are described by
+/// the [`GroupPolicy`].
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Eq, Hash, Debug, Deref)]
+#[reflect(Component, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvoidanceGroup(pub u32);
+
+/// How the agents of a group avoid the agents of another group.
+#[derive(Reflect, Clone, Copy, PartialEq, Debug, Default)]
+#[reflect(Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GroupInteraction {
+    /// The groups don't avoid each other.
+    Ignore,
+
+    /// The groups share the avoidance according to the responsibility of the agents.
+    #[default]
+    Reciprocal,
+
+    /// The first group yields fully to the second, which doesn't avoid it at all.
+    Yield,
+
+    /// The first group takes this share of the avoidance, between zero and one, and the
+    /// second group takes the rest.
+    Ratio(f32),
+}
+
+impl GroupInteraction {
+    /// The same interaction seen from the other group.
+    pub fn mirrored(self) -> GroupInteraction {
+        match self {
+            GroupInteraction::Ignore => GroupInteraction::Ignore,
+            GroupInteraction::Reciprocal => GroupInteraction::Reciprocal,
+            GroupInteraction::Yield => GroupInteraction::Ratio(0.0),
+            GroupInteraction::Ratio(share) => GroupInteraction::Ratio(1.0 - share),
+        }
+    }
+}
+
+/// Describes how [`AvoidanceGroup`]s interact with each other.
+///
+/// Agents without a group, and pairs of groups without a rule, use
+/// [`default`](Self::default).
+#[derive(Resource, Reflect, Clone, PartialEq, Debug, Default)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GroupPolicy {
+    pub default: GroupInteraction,
+    pub pairs: HashMap<(u32, u32), GroupInteraction>,
+}
+
+impl GroupPolicy {
+    /// Sets how group `a` interacts with group `b`, and the mirrored interaction of `b`
+    /// with `a`.
+    pub fn set(&mut self, a: u32, b: u32, interaction: GroupInteraction) -> &mut Self {
+        self.pairs.insert((a, b), interaction);
+        if a != b {
+            self.pairs.insert((b, a), interaction.mirrored());
+        }
+        self
+    }
+
+    /// How an agent of group `agent` avoids an agent of group `neighbour`.
+    pub fn interaction(
+        &self,
+        agent: Option<AvoidanceGroup>,
+        neighbour: Option<AvoidanceGroup>,
+    ) -> GroupInteraction {
+        let (Some(agent), Some(neighbour)) = (agent, neighbour) else {
+            return self.default;
+        };
+        self.pairs
+            .get(&(agent.0, neighbour.0))
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Adapts `neighbour` as seen by `agent` so that the solver splits the avoidance
+    /// according to the policy, or returns `None` when the neighbour is ignored.
+    pub fn apply(
+        &self,
+        agent: &Agent,
+        agent_group: Option<AvoidanceGroup>,
+        neighbour: &Agent,
+        neighbour_group: Option<AvoidanceGroup>,
+    ) -> Option<Agent> {
+        let share = match self.interaction(agent_group, neighbour_group) {
+            GroupInteraction::Ignore => return None,
+            GroupInteraction::Reciprocal => return Some(neighbour.clone()),
+            GroupInteraction::Yield => 1.0,
+            GroupInteraction::Ratio(share) => share.clamp(0.0, 1.0),
+        };
+        if share <= 0.0 {
+            return None;
+        }
+
+        // The solver gives the agent a share of `own / (own + neighbour)`.
+        let mut neighbour = neighbour.clone();
+        neighbour.avoidance_responsibility = agent.avoidance_responsibility * (1.0 - share) / share;
+        Some(neighbour)
+    }
+}
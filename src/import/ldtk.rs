@@ -0,0 +1,262 @@
+//! Obstacles from [LDtk](https://ldtk.io/) `.ldtk` projects.
+//!
+//! The solid cells of IntGrid layers, and of auto-layers built from an IntGrid, are
+//! merged into outlines per layer. Entities can be imported as rectangular obstacles by
+//! identifier, e.g. for doors or crates placed in an entity layer.
+
+use super::{cell_outlines, closed_ccw, ImportError};
+use bevy::math::{IVec2, Vec2};
+use bevy::utils::HashSet;
+use dodgy_2d::Obstacle;
+use serde_json::Value;
+use std::path::Path;
+
+/// Configures how an LDtk project is imported.
+#[derive(Clone, PartialEq, Debug)]
+pub struct LdtkImport {
+    /// The identifiers of the levels to import. All levels are imported when empty.
+    pub levels: Vec<String>,
+
+    /// The identifiers of the IntGrid layers to import. All layers with IntGrid values
+    /// are imported when empty.
+    pub layers: Vec<String>,
+
+    /// The IntGrid values counted as solid. Any non-zero value is solid when empty.
+    pub solid_values: Vec<i64>,
+
+    /// The identifiers of the entities imported as rectangular obstacles.
+    pub entities: Vec<String>,
+
+    /// The world units per pixel.
+    pub scale: f32,
+
+    /// The world position of the top-left corner of the LDtk world.
+    pub origin: Vec2,
+}
+
+impl Default for LdtkImport {
+    fn default() -> Self {
+        LdtkImport {
+            levels: vec![],
+            layers: vec![],
+            solid_values: vec![],
+            entities: vec![],
+            scale: 1.0,
+            origin: Vec2::ZERO,
+        }
+    }
+}
+
+impl LdtkImport {
+    pub fn with_levels(
+        mut self,
+        levels: impl IntoIterator<Item = impl Into<String>>,
+    ) -> LdtkImport {
+        self.levels = levels.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_layers(
+        mut self,
+        layers: impl IntoIterator<Item = impl Into<String>>,
+    ) -> LdtkImport {
+        self.layers = layers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_solid_values(mut self, solid_values: impl IntoIterator<Item = i64>) -> LdtkImport {
+        self.solid_values = solid_values.into_iter().collect();
+        self
+    }
+
+    pub fn with_entities(
+        mut self,
+        entities: impl IntoIterator<Item = impl Into<String>>,
+    ) -> LdtkImport {
+        self.entities = entities.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> LdtkImport {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> LdtkImport {
+        self.origin = origin;
+        self
+    }
+
+    /// Reads the obstacles of a `.ldtk` file, loading its external levels relative to it.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Vec<Obstacle>, ImportError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        self.parse_with(&text, |source| {
+            Ok(std::fs::read_to_string(directory.join(source))?)
+        })
+    }
+
+    /// Reads the obstacles of a project from the text of a `.ldtk` file. Projects saving
+    /// their levels in separate files must be read with [`load`](Self::load) instead.
+    pub fn parse(&self, text: &str) -> Result<Vec<Obstacle>, ImportError> {
+        self.parse_with(text, |source| {
+            Err(ImportError::Unsupported(format!(
+                "external level `{source}` without a project path"
+            )))
+        })
+    }
+
+    fn parse_with(
+        &self,
+        text: &str,
+        mut read_level: impl FnMut(&str) -> Result<String, ImportError>,
+    ) -> Result<Vec<Obstacle>, ImportError> {
+        let project: Value = serde_json::from_str(text)?;
+
+        // Multi-world projects list their levels per world.
+        let mut levels: Vec<&Value> = array(&project, "levels")?.iter().collect();
+        if let Some(worlds) = project.get("worlds").and_then(Value::as_array) {
+            for world in worlds {
+                levels.extend(array(world, "levels")?);
+            }
+        }
+
+        let mut obstacles = vec![];
+        for level in levels {
+            let identifier = string(level, "identifier")?;
+            if !self.levels.is_empty() && !self.levels.iter().any(|l| l == identifier) {
+                continue;
+            }
+            match level.get("externalRelPath").and_then(Value::as_str) {
+                Some(source) => {
+                    let level: Value = serde_json::from_str(&read_level(source)?)?;
+                    self.import_level(&level, &mut obstacles)?;
+                }
+                None => self.import_level(level, &mut obstacles)?,
+            }
+        }
+        Ok(obstacles)
+    }
+
+    fn import_level(
+        &self,
+        level: &Value,
+        obstacles: &mut Vec<Obstacle>,
+    ) -> Result<(), ImportError> {
+        let level_position = Vec2::new(
+            integer(level, "worldX")? as f32,
+            integer(level, "worldY")? as f32,
+        );
+        for layer in array(level, "layerInstances")? {
+            let layer_position = level_position
+                + Vec2::new(
+                    integer(layer, "__pxTotalOffsetX")? as f32,
+                    integer(layer, "__pxTotalOffsetY")? as f32,
+                );
+
+            for entity in array(layer, "entityInstances")? {
+                let identifier = string(entity, "__identifier")?;
+                if !self.entities.iter().any(|e| e == identifier) {
+                    continue;
+                }
+                let pivot = pair(entity, "__pivot")?;
+                let size = Vec2::new(
+                    integer(entity, "width")? as f32,
+                    integer(entity, "height")? as f32,
+                );
+                let top_left = layer_position + pair(entity, "px")? - pivot * size;
+                obstacles.push(closed_ccw(
+                    [
+                        top_left,
+                        top_left + Vec2::new(size.x, 0.0),
+                        top_left + size,
+                        top_left + Vec2::new(0.0, size.y),
+                    ]
+                    .into_iter()
+                    .map(|point| self.to_world(point))
+                    .collect(),
+                ));
+            }
+
+            let values = array(layer, "intGridCsv")?;
+            if values.is_empty() {
+                continue;
+            }
+            let identifier = string(layer, "__identifier")?;
+            if !self.layers.is_empty() && !self.layers.iter().any(|l| l == identifier) {
+                continue;
+            }
+            let width = integer(layer, "__cWid")?.max(1);
+            let grid_size = integer(layer, "__gridSize")? as f32;
+
+            // LDtk rows grow downwards, so flip them into y-up cells below the layer.
+            let cells: HashSet<IVec2> = values
+                .iter()
+                .enumerate()
+                .filter(|(_, value)| self.is_solid(value.as_i64().unwrap_or(0)))
+                .map(|(i, _)| {
+                    let i = i as i64;
+                    IVec2::new((i % width) as i32, -(i / width) as i32 - 1)
+                })
+                .collect();
+            obstacles.extend(cell_outlines(
+                &cells,
+                Vec2::splat(grid_size * self.scale),
+                self.to_world(layer_position),
+            ));
+        }
+        Ok(())
+    }
+
+    fn is_solid(&self, value: i64) -> bool {
+        if self.solid_values.is_empty() {
+            value != 0
+        } else {
+            self.solid_values.contains(&value)
+        }
+    }
+
+    /// Converts a position in LDtk world pixels into world units.
+    fn to_world(&self, point: Vec2) -> Vec2 {
+        self.origin + Vec2::new(point.x, -point.y) * self.scale
+    }
+}
+
+/// Reads an array field, treating a missing or null field as empty.
+fn array<'a>(value: &'a Value, field: &str) -> Result<&'a [Value], ImportError> {
+    match value.get(field) {
+        None | Some(Value::Null) => Ok(&[]),
+        Some(Value::Array(values)) => Ok(values),
+        Some(_) => Err(ImportError::Invalid(format!("`{field}` isn't an array"))),
+    }
+}
+
+fn string<'a>(value: &'a Value, field: &str) -> Result<&'a str, ImportError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| ImportError::Invalid(format!("missing string `{field}`")))
+}
+
+fn integer(value: &Value, field: &str) -> Result<i64, ImportError> {
+    value
+        .get(field)
+        .and_then(Value::as_i64)
+        .ok_or_else(|| ImportError::Invalid(format!("missing integer `{field}`")))
+}
+
+/// Reads a field holding two numbers, like a position or pivot.
+fn pair(value: &Value, field: &str) -> Result<Vec2, ImportError> {
+    match array(value, field)? {
+        [x, y] => match (x.as_f64(), y.as_f64()) {
+            (Some(x), Some(y)) => Ok(Vec2::new(x as f32, y as f32)),
+            _ => Err(ImportError::Invalid(format!(
+                "`{field}` isn't a pair of numbers"
+            ))),
+        },
+        _ => Err(ImportError::Invalid(format!(
+            "`{field}` isn't a pair of numbers"
+        ))),
+    }
+}
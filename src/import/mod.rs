@@ -0,0 +1,114 @@
+//! Loaders turning the collision authored in tilemap editors into dodgy obstacles.
+//!
+//! Solid tiles are merged into outlines, so a level contributes a handful of polygons
+//! rather than one obstacle per tile. Coordinates are converted to Bevy's y-up
+//! convention: the top-left corner of the map or world lands on the import origin.
+
+#[cfg(feature = "ldtk")]
+pub mod ldtk;
+#[cfg(feature = "tiled")]
+pub mod tiled;
+
+use crate::geometry::{signed_area, GridObstacleBuilder};
+use bevy::math::{IVec2, Vec2};
+use bevy::utils::HashSet;
+use dodgy_2d::Obstacle;
+use std::fmt;
+
+/// An error raised while importing obstacles from a tilemap file.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The file, or a file it references, couldn't be read.
+    Io(std::io::Error),
+
+    #[cfg(feature = "tiled")]
+    Xml(roxmltree::Error),
+
+    #[cfg(feature = "ldtk")]
+    Json(serde_json::Error),
+
+    /// The file is well-formed but doesn't describe a valid map.
+    Invalid(String),
+
+    /// The file uses a feature the loader doesn't handle.
+    Unsupported(String),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(error) => write!(f, "failed to read the map: {error}"),
+            #[cfg(feature = "tiled")]
+            ImportError::Xml(error) => write!(f, "failed to parse the map: {error}"),
+            #[cfg(feature = "ldtk")]
+            ImportError::Json(error) => write!(f, "failed to parse the map: {error}"),
+            ImportError::Invalid(reason) => write!(f, "invalid map: {reason}"),
+            ImportError::Unsupported(reason) => write!(f, "unsupported map: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImportError::Io(error) => Some(error),
+            #[cfg(feature = "tiled")]
+            ImportError::Xml(error) => Some(error),
+            #[cfg(feature = "ldtk")]
+            ImportError::Json(error) => Some(error),
+            ImportError::Invalid(_) | ImportError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ImportError {
+    fn from(error: std::io::Error) -> Self {
+        ImportError::Io(error)
+    }
+}
+
+#[cfg(feature = "tiled")]
+impl From<roxmltree::Error> for ImportError {
+    fn from(error: roxmltree::Error) -> Self {
+        ImportError::Xml(error)
+    }
+}
+
+#[cfg(feature = "ldtk")]
+impl From<serde_json::Error> for ImportError {
+    fn from(error: serde_json::Error) -> Self {
+        ImportError::Json(error)
+    }
+}
+
+/// Merges solid cells into closed outlines with a [`GridObstacleBuilder`] spanning them.
+///
+/// Cell `(x, y)` covers `origin + (x, y) * cell_size` to `origin + (x + 1, y + 1) * cell_size`.
+pub(crate) fn cell_outlines(
+    cells: &HashSet<IVec2>,
+    cell_size: Vec2,
+    origin: Vec2,
+) -> Vec<Obstacle> {
+    if cells.is_empty() {
+        return vec![];
+    }
+    let min = cells.iter().fold(IVec2::MAX, |min, cell| min.min(*cell));
+    let max = cells.iter().fold(IVec2::MIN, |max, cell| max.max(*cell));
+    let size = (max - min + IVec2::ONE).as_uvec2();
+
+    let mut builder = GridObstacleBuilder::new(size.x as usize, size.y as usize, cell_size)
+        .with_origin(origin + min.as_vec2() * cell_size);
+    for cell in cells {
+        let cell = (*cell - min).as_uvec2();
+        builder.set(cell.x as usize, cell.y as usize, true);
+    }
+    builder.build()
+}
+
+/// A closed obstacle with its vertices reordered counter-clockwise if needed.
+pub(crate) fn closed_ccw(mut vertices: Vec<Vec2>) -> Obstacle {
+    if signed_area(&vertices) < 0.0 {
+        vertices.reverse();
+    }
+    Obstacle::Closed { vertices }
+}
@@ -0,0 +1,501 @@
+//! Obstacles from [Tiled](https://www.mapeditor.org/) `.tmx` maps.
+//!
+//! Object layers contribute their rectangles, ellipses, polygons and polylines. Tile
+//! layers contribute the collision shapes edited on the tiles of their tilesets: tiles
+//! whose collision is a single rectangle covering the whole tile are merged with their
+//! solid neighbours into outlines, other collision shapes are placed individually.
+//!
+//! Only orthogonal maps are supported, with CSV, XML or uncompressed base64 tile data.
+
+use super::{cell_outlines, closed_ccw, ImportError};
+use bevy::math::{IVec2, Vec2};
+use bevy::utils::{HashMap, HashSet};
+use dodgy_2d::Obstacle;
+use roxmltree::{Document, Node};
+use std::f32::consts::TAU;
+use std::path::Path;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x8000_0000;
+const FLIPPED_VERTICALLY: u32 = 0x4000_0000;
+const FLIPPED_DIAGONALLY: u32 = 0x2000_0000;
+const GID_MASK: u32 = 0x0FFF_FFFF;
+
+/// The number of segments approximating ellipse objects.
+const ELLIPSE_SEGMENTS: usize = 16;
+
+/// Configures how a Tiled map is imported.
+#[derive(Clone, PartialEq, Debug)]
+pub struct TiledImport {
+    /// The names of the layers to import. All layers are imported when empty.
+    pub layers: Vec<String>,
+
+    /// The world units per map pixel.
+    pub scale: f32,
+
+    /// The world position of the top-left corner of the map.
+    pub origin: Vec2,
+}
+
+impl Default for TiledImport {
+    fn default() -> Self {
+        TiledImport {
+            layers: vec![],
+            scale: 1.0,
+            origin: Vec2::ZERO,
+        }
+    }
+}
+
+impl TiledImport {
+    pub fn with_layers(
+        mut self,
+        layers: impl IntoIterator<Item = impl Into<String>>,
+    ) -> TiledImport {
+        self.layers = layers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> TiledImport {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> TiledImport {
+        self.origin = origin;
+        self
+    }
+
+    /// Reads the obstacles of a `.tmx` file, loading its external tilesets relative to it.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Vec<Obstacle>, ImportError> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        self.parse_with(&text, |source| {
+            Ok(std::fs::read_to_string(directory.join(source))?)
+        })
+    }
+
+    /// Reads the obstacles of a map from the text of a `.tmx` file. Maps referencing
+    /// external tilesets must be read with [`load`](Self::load) instead.
+    pub fn parse(&self, text: &str) -> Result<Vec<Obstacle>, ImportError> {
+        self.parse_with(text, |source| {
+            Err(ImportError::Unsupported(format!(
+                "external tileset `{source}` without a map path"
+            )))
+        })
+    }
+
+    fn parse_with(
+        &self,
+        text: &str,
+        mut read_tileset: impl FnMut(&str) -> Result<String, ImportError>,
+    ) -> Result<Vec<Obstacle>, ImportError> {
+        let document = Document::parse(text)?;
+        let map = document.root_element();
+        if !map.has_tag_name("map") {
+            return Err(ImportError::Invalid("the root element isn't a map".into()));
+        }
+        let orientation = map.attribute("orientation").unwrap_or("orthogonal");
+        if orientation != "orthogonal" {
+            return Err(ImportError::Unsupported(format!("{orientation} maps")));
+        }
+        let tile_size = Vec2::new(number(map, "tilewidth")?, number(map, "tileheight")?);
+
+        let mut tilesets = vec![];
+        for tileset in map.children().filter(|n| n.has_tag_name("tileset")) {
+            let first_gid = number::<u32>(tileset, "firstgid")?;
+            let collisions = match tileset.attribute("source") {
+                Some(source) => {
+                    let text = read_tileset(source)?;
+                    let document = Document::parse(&text)?;
+                    tile_collisions(document.root_element())?
+                }
+                None => tile_collisions(tileset)?,
+            };
+            tilesets.push((first_gid, collisions));
+        }
+        tilesets.sort_by_key(|(first_gid, _)| *first_gid);
+
+        let mut obstacles = vec![];
+        let mut solid_cells = HashSet::default();
+        for layer in map.descendants().filter(|n| self.imports(*n)) {
+            let offset = Vec2::new(
+                number(layer, "offsetx").unwrap_or(0.0),
+                number(layer, "offsety").unwrap_or(0.0),
+            );
+            match layer.tag_name().name() {
+                "objectgroup" => {
+                    for object in layer.children().filter(|n| n.has_tag_name("object")) {
+                        if let Some(shape) = object_shape(object)? {
+                            obstacles.push(self.to_world(shape.translated(offset)));
+                        }
+                    }
+                }
+                "layer" => {
+                    for (cell, gid) in layer_tiles(layer)? {
+                        let Some(collision) = find_collision(&tilesets, gid & GID_MASK) else {
+                            continue;
+                        };
+                        match collision {
+                            TileCollision::Full if offset == Vec2::ZERO => {
+                                solid_cells.insert(cell);
+                            }
+                            TileCollision::Full => obstacles.push(
+                                self.to_world(
+                                    Shape::rect(Vec2::ZERO, tile_size)
+                                        .translated(offset + cell.as_vec2() * tile_size),
+                                ),
+                            ),
+                            TileCollision::Shapes { size, shapes } => {
+                                // Tiles larger than the grid are anchored to the bottom-left
+                                // corner of their cell.
+                                let position = offset
+                                    + Vec2::new(
+                                        cell.x as f32 * tile_size.x,
+                                        (cell.y + 1) as f32 * tile_size.y - size.y,
+                                    );
+                                for shape in shapes {
+                                    let shape =
+                                        shape.clone().flipped(gid, *size).translated(position);
+                                    obstacles.push(self.to_world(shape));
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        // Tiled rows grow downwards, so flip them into y-up cells below the origin.
+        let cells: HashSet<IVec2> = solid_cells
+            .into_iter()
+            .map(|cell| IVec2::new(cell.x, -cell.y - 1))
+            .collect();
+        obstacles.extend(cell_outlines(&cells, tile_size * self.scale, self.origin));
+        Ok(obstacles)
+    }
+
+    /// Whether a node is a tile or object layer selected for import.
+    fn imports(&self, node: Node) -> bool {
+        if !node.has_tag_name("layer") && !node.has_tag_name("objectgroup") {
+            return false;
+        }
+        // Skip the collision shapes nested in tilesets.
+        if node.ancestors().any(|n| n.has_tag_name("tileset")) {
+            return false;
+        }
+        self.layers.is_empty()
+            || node
+                .attribute("name")
+                .is_some_and(|name| self.layers.iter().any(|layer| layer == name))
+    }
+
+    /// Converts a shape in map pixels into an obstacle in world units.
+    fn to_world(&self, shape: Shape) -> Obstacle {
+        let vertices = shape
+            .points
+            .into_iter()
+            .map(|point| self.origin + Vec2::new(point.x, -point.y) * self.scale)
+            .collect();
+        if shape.closed {
+            closed_ccw(vertices)
+        } else {
+            Obstacle::Open { vertices }
+        }
+    }
+}
+
+/// A shape in map pixels, with y pointing down.
+#[derive(Clone, Debug)]
+struct Shape {
+    points: Vec<Vec2>,
+    closed: bool,
+}
+
+impl Shape {
+    fn rect(position: Vec2, size: Vec2) -> Shape {
+        Shape {
+            points: vec![
+                position,
+                position + Vec2::new(size.x, 0.0),
+                position + size,
+                position + Vec2::new(0.0, size.y),
+            ],
+            closed: true,
+        }
+    }
+
+    fn translated(mut self, offset: Vec2) -> Shape {
+        for point in &mut self.points {
+            *point += offset;
+        }
+        self
+    }
+
+    /// Applies the flip flags of a tile's gid to a shape within a tile of `size`.
+    fn flipped(mut self, gid: u32, size: Vec2) -> Shape {
+        let mut size = size;
+        if gid & FLIPPED_DIAGONALLY != 0 {
+            for point in &mut self.points {
+                *point = Vec2::new(point.y, point.x);
+            }
+            size = Vec2::new(size.y, size.x);
+        }
+        if gid & FLIPPED_HORIZONTALLY != 0 {
+            for point in &mut self.points {
+                point.x = size.x - point.x;
+            }
+        }
+        if gid & FLIPPED_VERTICALLY != 0 {
+            for point in &mut self.points {
+                point.y = size.y - point.y;
+            }
+        }
+        self
+    }
+}
+
+/// The collision edited on a tile of a tileset.
+#[derive(Clone, Debug)]
+enum TileCollision {
+    /// A single rectangle covering the whole tile.
+    Full,
+
+    /// Any other shapes, relative to the top-left corner of a tile of `size`.
+    Shapes { size: Vec2, shapes: Vec<Shape> },
+}
+
+/// Reads the collision shapes of the tiles of a tileset, by local tile id.
+fn tile_collisions(tileset: Node) -> Result<HashMap<u32, TileCollision>, ImportError> {
+    let size = Vec2::new(
+        number(tileset, "tilewidth")?,
+        number(tileset, "tileheight")?,
+    );
+
+    let mut collisions = HashMap::default();
+    for tile in tileset.children().filter(|n| n.has_tag_name("tile")) {
+        let Some(group) = tile.children().find(|n| n.has_tag_name("objectgroup")) else {
+            continue;
+        };
+        let mut shapes = vec![];
+        for object in group.children().filter(|n| n.has_tag_name("object")) {
+            if let Some(shape) = object_shape(object)? {
+                shapes.push(shape);
+            }
+        }
+        if shapes.is_empty() {
+            continue;
+        }
+
+        let full = shapes.len() == 1
+            && shapes[0].closed
+            && shapes[0].points == Shape::rect(Vec2::ZERO, size).points;
+        let collision = if full {
+            TileCollision::Full
+        } else {
+            TileCollision::Shapes { size, shapes }
+        };
+        collisions.insert(number(tile, "id")?, collision);
+    }
+    Ok(collisions)
+}
+
+/// Finds the collision of a global tile id among the tilesets, sorted by first gid.
+fn find_collision(
+    tilesets: &[(u32, HashMap<u32, TileCollision>)],
+    gid: u32,
+) -> Option<&TileCollision> {
+    if gid == 0 {
+        return None;
+    }
+    let (first_gid, collisions) = tilesets
+        .iter()
+        .rev()
+        .find(|(first_gid, _)| *first_gid <= gid)?;
+    collisions.get(&(gid - first_gid))
+}
+
+/// Reads the shape of an object, or `None` for points, texts and tile objects.
+fn object_shape(object: Node) -> Result<Option<Shape>, ImportError> {
+    if object.has_attribute("gid") {
+        return Ok(None);
+    }
+    let position = Vec2::new(
+        number(object, "x").unwrap_or(0.0),
+        number(object, "y").unwrap_or(0.0),
+    );
+    let size = Vec2::new(
+        number(object, "width").unwrap_or(0.0),
+        number(object, "height").unwrap_or(0.0),
+    );
+    let rotation = number::<f32>(object, "rotation")
+        .unwrap_or(0.0)
+        .to_radians();
+
+    let mut shape = None;
+    for child in object.children().filter(Node::is_element) {
+        shape = match child.tag_name().name() {
+            "polygon" => Some(Shape {
+                points: points(child)?,
+                closed: true,
+            }),
+            "polyline" => Some(Shape {
+                points: points(child)?,
+                closed: false,
+            }),
+            "ellipse" => Some(Shape {
+                points: (0..ELLIPSE_SEGMENTS)
+                    .map(|i| {
+                        let direction = Vec2::from_angle(TAU * i as f32 / ELLIPSE_SEGMENTS as f32);
+                        size * 0.5 * (Vec2::ONE + direction)
+                    })
+                    .collect(),
+                closed: true,
+            }),
+            "point" | "text" => return Ok(None),
+            _ => continue,
+        };
+    }
+    let shape = match shape {
+        Some(shape) => shape,
+        None if size.x > 0.0 && size.y > 0.0 => Shape::rect(Vec2::ZERO, size),
+        None => return Ok(None),
+    };
+
+    // Objects rotate clockwise around their position, which is a positive angle with y
+    // pointing down.
+    let rotation = Vec2::from_angle(rotation);
+    Ok(Some(Shape {
+        points: shape
+            .points
+            .into_iter()
+            .map(|point| position + rotation.rotate(point))
+            .collect(),
+        closed: shape.closed,
+    }))
+}
+
+/// Parses the `points` attribute of a polygon or polyline.
+fn points(node: Node) -> Result<Vec<Vec2>, ImportError> {
+    let text = node.attribute("points").unwrap_or_default();
+    text.split_whitespace()
+        .map(|pair| {
+            let (x, y) = pair
+                .split_once(',')
+                .ok_or_else(|| ImportError::Invalid(format!("malformed point `{pair}`")))?;
+            Ok(Vec2::new(parse(x)?, parse(y)?))
+        })
+        .collect()
+}
+
+/// Reads the non-empty tiles of a tile layer with their cell, rows growing downwards.
+fn layer_tiles(layer: Node) -> Result<Vec<(IVec2, u32)>, ImportError> {
+    let Some(data) = layer.children().find(|n| n.has_tag_name("data")) else {
+        return Ok(vec![]);
+    };
+    let encoding = data.attribute("encoding");
+    if let Some(compression) = data.attribute("compression") {
+        return Err(ImportError::Unsupported(format!(
+            "{compression} compressed tile data"
+        )));
+    }
+
+    // Infinite maps store their tiles in chunks.
+    let chunks: Vec<Node> = data
+        .children()
+        .filter(|n| n.has_tag_name("chunk"))
+        .collect();
+    let regions = if chunks.is_empty() {
+        vec![(data, IVec2::ZERO, number(layer, "width")?)]
+    } else {
+        chunks
+            .into_iter()
+            .map(|chunk| {
+                let position = IVec2::new(number(chunk, "x")?, number(chunk, "y")?);
+                Ok((chunk, position, number(chunk, "width")?))
+            })
+            .collect::<Result<_, ImportError>>()?
+    };
+
+    let mut tiles = vec![];
+    for (region, position, width) in regions {
+        let gids: Vec<u32> = match encoding {
+            Some("csv") => region
+                .text()
+                .unwrap_or_default()
+                .split(',')
+                .map(|gid| parse(gid.trim()))
+                .collect::<Result<_, _>>()?,
+            Some("base64") => decode_base64(region.text().unwrap_or_default())?
+                .chunks_exact(4)
+                .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect(),
+            None => region
+                .children()
+                .filter(|n| n.has_tag_name("tile"))
+                .map(|tile| number(tile, "gid").or(Ok(0)))
+                .collect::<Result<_, ImportError>>()?,
+            Some(encoding) => {
+                return Err(ImportError::Unsupported(format!("{encoding} tile data")));
+            }
+        };
+        let width = width.max(1);
+        tiles.extend(
+            gids.into_iter()
+                .enumerate()
+                .filter(|(_, gid)| *gid != 0)
+                .map(|(i, gid)| {
+                    (
+                        position + IVec2::new(i as i32 % width, i as i32 / width),
+                        gid,
+                    )
+                }),
+        );
+    }
+    Ok(tiles)
+}
+
+/// Decodes standard base64, ignoring whitespace.
+fn decode_base64(text: &str) -> Result<Vec<u8>, ImportError> {
+    let mut bytes = vec![];
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for c in text
+        .bytes()
+        .filter(|c| !c.is_ascii_whitespace() && *c != b'=')
+    {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(ImportError::Invalid("malformed base64 tile data".into())),
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+/// Reads a numeric attribute of a node.
+fn number<T: std::str::FromStr>(node: Node, attribute: &str) -> Result<T, ImportError> {
+    let value = node.attribute(attribute).ok_or_else(|| {
+        ImportError::Invalid(format!(
+            "missing `{attribute}` on <{}>",
+            node.tag_name().name()
+        ))
+    })?;
+    parse(value)
+}
+
+fn parse<T: std::str::FromStr>(value: &str) -> Result<T, ImportError> {
+    value
+        .parse()
+        .map_err(|_| ImportError::Invalid(format!("`{value}` isn't a valid number")))
+}
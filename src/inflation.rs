@@ -0,0 +1,172 @@
+use crate::agents::AgentInfo;
+use crate::geometry::{inflate_obstacle, CornerStyle};
+use crate::obstacles::{
+    AsObstacle, DodgyObstacle, DodgyObstacleIndex, ObstacleConversion, TransformObstacle,
+};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use dodgy_2d::Obstacle;
+
+/// Grows the obstacles seen by agents of each radius class, so that large agents go
+/// around corners smoothly instead of catching on them.
+///
+/// This is a pre-pass on top of the obstacle margin:
+/// the inflated variants are computed only when an obstacle changes and cached in its
+/// [`InflatedObstacles`]. It is disabled while there are no classes.
+#[derive(Resource, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObstacleInflation {
+    /// The classes, sorted by increasing [`max_radius`](RadiusClass::max_radius).
+    pub classes: Vec<RadiusClass>,
+
+    pub corners: CornerStyle,
+}
+
+impl Default for ObstacleInflation {
+    fn default() -> Self {
+        ObstacleInflation {
+            classes: vec![],
+            corners: CornerStyle::Round,
+        }
+    }
+}
+
+/// The agents up to a radius, and how much obstacles grow for them.
+#[derive(Reflect, Clone, Copy, PartialEq, Debug)]
+#[reflect(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RadiusClass {
+    pub max_radius: f32,
+    pub inflation: f32,
+}
+
+impl ObstacleInflation {
+    /// Small, medium and large classes, each inflating obstacles by `inflation` times
+    /// their max radius.
+    pub fn small_medium_large(small: f32, medium: f32, large: f32, inflation: f32) -> Self {
+        ObstacleInflation::default()
+            .with_class(small, small * inflation)
+            .with_class(medium, medium * inflation)
+            .with_class(large, large * inflation)
+    }
+
+    /// Adds a class for agents up to `max_radius`, keeping the classes sorted.
+    pub fn with_class(mut self, max_radius: f32, inflation: f32) -> Self {
+        self.classes.push(RadiusClass {
+            max_radius,
+            inflation,
+        });
+        self.classes
+            .sort_by(|a, b| a.max_radius.total_cmp(&b.max_radius));
+        self
+    }
+
+    pub fn with_corners(mut self, corners: CornerStyle) -> Self {
+        self.corners = corners;
+        self
+    }
+
+    /// The index of the class of an agent, the largest class for agents bigger than all.
+    pub fn class_of(&self, radius: f32) -> Option<usize> {
+        if self.classes.is_empty() {
+            return None;
+        }
+        Some(
+            self.classes
+                .iter()
+                .position(|class| radius <= class.max_radius)
+                .unwrap_or(self.classes.len() - 1),
+        )
+    }
+
+    /// The variants of world-space obstacles for every class.
+    fn inflate(&self, obstacles: &[Obstacle], segments: usize) -> Vec<Vec<Obstacle>> {
+        self.classes
+            .iter()
+            .map(|class| {
+                obstacles
+                    .iter()
+                    .map(|obstacle| {
+                        inflate_obstacle(obstacle, class.inflation, self.corners, segments)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The world-space obstacles of an entity inflated for each [`RadiusClass`], used by the
+/// avoidance in place of the original obstacles.
+#[derive(Component, Clone, Default, Debug)]
+pub struct InflatedObstacles {
+    /// The obstacles of each class, in the order of [`ObstacleInflation::classes`].
+    pub classes: Vec<Vec<Obstacle>>,
+}
+
+/// Recomputes the [`InflatedObstacles`] of the static colliders and [`DodgyObstacle`]s that
+/// changed, or of all of them when the settings changed.
+pub(crate) fn update_inflated_obstacles(
+    mut commands: Commands,
+    inflation: Res<ObstacleInflation>,
+    conversion: Res<ObstacleConversion>,
+    index: Res<DodgyObstacleIndex>,
+    colliders: Query<
+        (
+            Entity,
+            Ref<Transform>,
+            Ref<Collider>,
+            &RigidBody,
+            Has<InflatedObstacles>,
+        ),
+        Without<AgentInfo>,
+    >,
+    dodgy_obstacles: Query<(
+        Entity,
+        Ref<Transform>,
+        Ref<DodgyObstacle>,
+        Has<InflatedObstacles>,
+    )>,
+    inflated: Query<Entity, With<InflatedObstacles>>,
+) {
+    let settings_changed = inflation.is_changed() || conversion.is_changed();
+    if inflation.classes.is_empty() {
+        if settings_changed {
+            for entity in inflated.iter() {
+                commands.entity(entity).remove::<InflatedObstacles>();
+            }
+        }
+        return;
+    }
+
+    for (entity, tf, collider, body, cached) in colliders.iter() {
+        if !body.is_static() {
+            continue;
+        }
+        if cached && !settings_changed && !tf.is_changed() && !collider.is_changed() {
+            continue;
+        }
+        let mut obstacles = collider.to_obstacles(&conversion);
+        for obstacle in obstacles.iter_mut() {
+            obstacle.transform_points(&tf);
+        }
+        commands.entity(entity).insert(InflatedObstacles {
+            classes: inflation.inflate(&obstacles, conversion.circle_segments),
+        });
+    }
+
+    for (entity, tf, obstacle, cached) in dodgy_obstacles.iter() {
+        if cached && !settings_changed && !tf.is_changed() && !obstacle.is_changed() {
+            continue;
+        }
+        let Some(entry) = index.get(entity) else {
+            continue;
+        };
+        commands.entity(entity).insert(InflatedObstacles {
+            classes: inflation.inflate(
+                std::slice::from_ref(&entry.obstacle),
+                conversion.circle_segments,
+            ),
+        });
+    }
+}
@@ -1,11 +1,51 @@
 pub mod agents;
+pub mod constraints;
 pub mod debug;
+pub mod diagnostics;
+pub mod flocking;
+pub mod formations;
 pub mod geometry;
-mod obstacles;
+pub mod groups;
+#[cfg(any(feature = "tiled", feature = "ldtk"))]
+pub mod import;
+pub mod inflation;
+pub mod lod;
+pub mod motion;
+pub mod obstacles;
+pub mod side;
+pub mod sleep;
+pub mod stuck;
+pub mod testing;
+pub mod validation;
 mod systems;
 
-use crate::systems::{on_add_create_collider, rvo_avoidance};
-use bevy::app::{App, Plugin, PreUpdate, Update};
+use crate::agents::{AgentGoal, AgentInfo, AvoidanceOptionsComponent, AvoidanceResult, StableId};
+use crate::diagnostics::{clear_avoidance_stats, AvoidanceStats};
+use crate::flocking::Flocking;
+use crate::formations::{update_formations, Formation, FormationShape};
+use crate::geometry::CornerStyle;
+use crate::groups::{AvoidanceGroup, GroupInteraction, GroupPolicy};
+use crate::inflation::{update_inflated_obstacles, ObstacleInflation, RadiusClass};
+use crate::lod::{AvoidanceFocus, AvoidanceLod};
+use crate::motion::{update_obstacle_velocities, ObstacleMotion, ObstacleVelocity};
+use crate::obstacles::{
+    update_obstacle_index, DodgyObstacle, DodgyObstacleIndex, ObstacleConversion,
+};
+use crate::side::{SidePreference, SidePreferenceConfig};
+use crate::sleep::{
+    wake_agents_near_moved_obstacles, wake_disturbed_agents, AgentSleepConfig, AgentSleepTimer,
+    AgentSleeping,
+};
+use crate::stuck::{
+    detect_stuck_agents, AgentStuck, StuckDetector, StuckRecovery, StuckRecoveryState,
+};
+use crate::systems::rvo_avoidance;
+use crate::validation::{report_invalid_obstacles, Winding};
+use bevy::app::{App, First, FixedUpdate, Plugin, Update};
+use bevy::ecs::schedule::SystemConfigs;
+use bevy::prelude::{
+    resource_equals, IntoSystemConfigs, Reflect, ReflectDefault, ReflectResource, Resource,
+};
 
 pub use dodgy_2d::AvoidanceOptions;
 
@@ -13,7 +53,89 @@ pub struct DodgyPlugin;
 
 impl Plugin for DodgyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(PreUpdate, on_add_create_collider)
-            .add_systems(Update, rvo_avoidance);
+        app.register_type::<AgentInfo>()
+            .register_type::<AgentGoal>()
+            .register_type::<AvoidanceOptionsComponent>()
+            .register_type::<AvoidanceResult>()
+            .register_type::<StableId>()
+            .register_type::<AgentSleeping>()
+            .register_type::<AgentSleepTimer>()
+            .register_type::<AvoidanceFocus>()
+            .register_type::<AvoidanceMode>()
+            .register_type::<AvoidanceLod>()
+            .register_type::<AgentSleepConfig>()
+            .register_type::<DodgyObstacle>()
+            .register_type::<Flocking>()
+            .register_type::<Formation>()
+            .register_type::<FormationShape>()
+            .register_type::<AvoidanceGroup>()
+            .register_type::<CornerStyle>()
+            .register_type::<GroupInteraction>()
+            .register_type::<GroupPolicy>()
+            .register_type::<ObstacleConversion>()
+            .register_type::<ObstacleInflation>()
+            .register_type::<ObstacleMotion>()
+            .register_type::<ObstacleVelocity>()
+            .register_type::<RadiusClass>()
+            .register_type::<SidePreference>()
+            .register_type::<SidePreferenceConfig>()
+            .register_type::<StuckDetector>()
+            .register_type::<StuckRecovery>()
+            .register_type::<StuckRecoveryState>()
+            .register_type::<Winding>()
+            .add_event::<AgentStuck>()
+            .init_resource::<AvoidanceMode>()
+            .init_resource::<AvoidanceLod>()
+            .init_resource::<AgentSleepConfig>()
+            .init_resource::<AvoidanceStats>()
+            .init_resource::<SidePreferenceConfig>()
+            .init_resource::<GroupPolicy>()
+            .init_resource::<DodgyObstacleIndex>()
+            .init_resource::<ObstacleConversion>()
+            .init_resource::<ObstacleInflation>()
+            .init_resource::<ObstacleMotion>()
+            .add_systems(First, clear_avoidance_stats)
+            .add_systems(
+                Update,
+                avoidance_systems().run_if(resource_equals(AvoidanceMode::Frame)),
+            )
+            .add_systems(
+                FixedUpdate,
+                avoidance_systems().run_if(resource_equals(AvoidanceMode::Deterministic)),
+            );
     }
 }
+
+fn avoidance_systems() -> SystemConfigs {
+    (
+        (update_formations, update_obstacle_index),
+        (
+            update_inflated_obstacles,
+            update_obstacle_velocities,
+            report_invalid_obstacles,
+            wake_disturbed_agents,
+            wake_agents_near_moved_obstacles,
+        ),
+        rvo_avoidance,
+        detect_stuck_agents,
+    )
+        .chain()
+}
+
+/// Selects how and when the avoidance velocities are solved.
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AvoidanceMode {
+    /// Solves once per frame in `Update` using the variable frame delta.
+    #[default]
+    Frame,
+
+    /// Solves in `FixedUpdate` using the fixed timestep, with agents, neighbours and
+    /// obstacles sorted by [`StableId`](agents::StableId) (or entity when absent) so
+    /// every peer of a lockstep simulation computes identical velocities.
+    ///
+    /// The physics engine must be deterministic as well, e.g. avian's
+    /// `enhanced-determinism` feature.
+    Deterministic,
+}
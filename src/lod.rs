@@ -0,0 +1,99 @@
+use bevy::prelude::{
+    Component, Reflect, ReflectComponent, ReflectDefault, ReflectResource, Resource, Vec2,
+};
+
+/// Marks an entity around which agents are always solved at full rate.
+///
+/// Cameras are treated as foci as well, so this is only needed when the interesting
+/// area isn't where a camera is, e.g. a player character on a server.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvoidanceFocus;
+
+/// Level-of-detail settings for the avoidance solve.
+///
+/// Agents within [`full_rate_distance`](Self::full_rate_distance) of a camera or an
+/// [`AvoidanceFocus`] are solved every tick. Agents further away are split into
+/// round-robin buckets and solved once every [`buckets`](Self::buckets) ticks, reusing
+/// their last result in between.
+///
+/// In [`AvoidanceMode::Deterministic`](crate::AvoidanceMode) only [`AvoidanceFocus`]
+/// entities are foci: cameras differ between the peers of a lockstep simulation, and
+/// selecting agents by them would desync it.
+#[derive(Resource, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AvoidanceLod {
+    /// Distance from the closest focus below which agents are solved every tick.
+    /// Defaults to infinity, which disables the level of detail.
+    pub full_rate_distance: f32,
+
+    /// The number of round-robin buckets distant agents are split into.
+    pub buckets: u32,
+
+    /// The maximum number of agents solved per tick. Agents over budget keep their last
+    /// result and are prioritised on the following ticks.
+    pub agent_budget: Option<usize>,
+}
+
+impl Default for AvoidanceLod {
+    fn default() -> Self {
+        Self {
+            full_rate_distance: f32::INFINITY,
+            buckets: 4,
+            agent_budget: None,
+        }
+    }
+}
+
+impl AvoidanceLod {
+    /// Whether an agent is close enough to a focus to be solved every tick.
+    pub fn is_near(&self, position: Vec2, foci: &[Vec2]) -> bool {
+        if self.full_rate_distance == f32::INFINITY {
+            return true;
+        }
+
+        let max_distance_squared = self.full_rate_distance * self.full_rate_distance;
+        foci.iter()
+            .any(|focus| focus.distance_squared(position) <= max_distance_squared)
+    }
+
+    /// Whether a distant agent is due on `tick`, either because its bucket comes up or
+    /// because it missed its turn.
+    pub fn is_due(&self, key: u64, tick: u32, last_solved_tick: u32) -> bool {
+        let buckets = self.buckets.max(1);
+        let bucket = (key % buckets as u64) as u32;
+        tick % buckets == bucket || tick.wrapping_sub(last_solved_tick) > buckets
+    }
+}
+
+/// An agent waiting to be solved this tick.
+pub(crate) struct LodCandidate<T> {
+    pub item: T,
+    pub near: bool,
+    pub last_solved_tick: u32,
+}
+
+/// Keeps the candidates that fit in the agent budget, preferring near agents and then
+/// those that have waited the longest. Ties keep their original order.
+pub(crate) fn apply_budget<T>(
+    candidates: &mut Vec<LodCandidate<T>>,
+    budget: Option<usize>,
+    tick: u32,
+) {
+    let Some(budget) = budget else {
+        return;
+    };
+    if candidates.len() <= budget {
+        return;
+    }
+
+    candidates.sort_by_key(|candidate| {
+        (
+            !candidate.near,
+            std::cmp::Reverse(tick.wrapping_sub(candidate.last_solved_tick)),
+        )
+    });
+    candidates.truncate(budget);
+}
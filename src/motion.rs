@@ -0,0 +1,114 @@
+use crate::agents::AgentInfo;
+use crate::obstacles::DodgyObstacle;
+use avian2d::prelude::*;
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+
+/// The velocity of a moving obstacle, like a sliding door or a rotating gate.
+///
+/// It is tracked for every static or kinematic collider and [`DodgyObstacle`] whose
+/// [`Transform`] changes, from the physics velocities of kinematic bodies or else from the
+/// change of their transform. Agents avoid the area a moving obstacle sweeps over the
+/// next [`ObstacleMotion::lookahead`] seconds rather than where it stands.
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Debug, Default)]
+#[reflect(Component, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObstacleVelocity {
+    pub linear: Vec2,
+
+    /// The rotation speed around the translation of the obstacle, in radians per second.
+    pub angular: f32,
+}
+
+impl ObstacleVelocity {
+    pub fn is_moving(&self) -> bool {
+        self.linear != Vec2::ZERO || self.angular != 0.0
+    }
+}
+
+/// Configures how moving obstacles are presented to the avoidance.
+#[derive(Resource, Reflect, Clone, Copy, PartialEq, Debug)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObstacleMotion {
+    /// How far ahead, in seconds, the motion of obstacles is swept.
+    pub lookahead: f32,
+
+    /// The number of future poses sampled over the lookahead.
+    pub samples: usize,
+}
+
+impl Default for ObstacleMotion {
+    fn default() -> Self {
+        ObstacleMotion {
+            lookahead: 0.5,
+            samples: 4,
+        }
+    }
+}
+
+/// Updates the [`ObstacleVelocity`] of the obstacles that moved, and stops those that
+/// didn't.
+pub(crate) fn update_obstacle_velocities(
+    mut commands: Commands,
+    mut obstacles: Query<
+        (
+            Entity,
+            Ref<Transform>,
+            Option<&RigidBody>,
+            Option<&LinearVelocity>,
+            Option<&AngularVelocity>,
+            Option<&mut ObstacleVelocity>,
+        ),
+        (
+            Or<(With<Collider>, With<DodgyObstacle>)>,
+            Without<AgentInfo>,
+        ),
+    >,
+    mut previous: Local<EntityHashMap<(Vec2, f32)>>,
+    time: Res<Time>,
+) {
+    let delta_secs = time.delta_secs();
+    if !(delta_secs > 0.0) {
+        return;
+    }
+
+    // Only the poses of the obstacles moving or just moved are kept.
+    let mut poses = EntityHashMap::default();
+    for (entity, tf, body, linvel, angvel, velocity) in obstacles.iter_mut() {
+        if body.is_some_and(RigidBody::is_dynamic) {
+            continue;
+        }
+        if !tf.is_changed() && velocity.is_none() {
+            continue;
+        }
+        let pose = (tf.translation.xy(), tf.rotation.to_euler(EulerRot::ZYX).0);
+        poses.insert(entity, pose);
+
+        let new_velocity = match (body, linvel, angvel) {
+            (Some(RigidBody::Kinematic), Some(linvel), Some(angvel)) => ObstacleVelocity {
+                linear: linvel.0,
+                angular: angvel.0,
+            },
+            _ if !tf.is_changed() => ObstacleVelocity::default(),
+            _ => match previous.get(&entity) {
+                Some((translation, angle)) => ObstacleVelocity {
+                    linear: (pose.0 - *translation) / delta_secs,
+                    angular: ((pose.1 - angle + PI).rem_euclid(TAU) - PI) / delta_secs,
+                },
+                None => ObstacleVelocity::default(),
+            },
+        };
+        match velocity {
+            Some(mut velocity) => {
+                velocity.set_if_neq(new_velocity);
+            }
+            None if new_velocity.is_moving() => {
+                commands.entity(entity).insert(new_velocity);
+            }
+            None => {}
+        }
+    }
+    *previous = poses;
+}
@@ -1,108 +1,444 @@
-use crate::geometry::{point_on_circle, rect_inner};
+use crate::agents::StableId;
+use crate::geometry::{
+    merge_shared_edges, rect_inner, rounded_convex, signed_area, union_obstacles,
+};
+use crate::validation::{correct_winding, Winding};
+use avian2d::parry::math::Point;
 use avian2d::parry::shape::TypedShape;
 use avian2d::prelude::*;
+use bevy::ecs::entity::{EntityHashMap, EntityHashSet};
 use bevy::prelude::*;
+use bevy::utils::HashMap;
 use dodgy_2d::Obstacle;
 
+/// Configures how collider shapes are converted into obstacles.
+#[derive(Resource, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObstacleConversion {
+    /// The number of segments approximating a full circle, for balls, capsules and
+    /// rounded corners.
+    pub circle_segments: usize,
+
+    /// When set, the closed parts of compound colliders are merged into single outlines by
+    /// rasterizing them into cells of this size.
+    ///
+    /// Otherwise the edges of the parts are chained with
+    /// [`merge_shared_edges`](crate::geometry::merge_shared_edges): parts sharing whole
+    /// edges, like those of a convex decomposition, merge exactly, and other parts keep
+    /// their outline. Overlapping parts whose vertices coincide may be chained into a
+    /// single outline crossing itself, and agents may snag on the seams where parts
+    /// overlap.
+    pub compound_union_cell_size: Option<f32>,
+}
+
+impl Default for ObstacleConversion {
+    fn default() -> Self {
+        ObstacleConversion {
+            circle_segments: 12,
+            compound_union_cell_size: None,
+        }
+    }
+}
+
 pub trait AsObstacle {
-    fn to_obstacle(&self) -> Option<Obstacle>;
+    fn to_obstacles(&self, conversion: &ObstacleConversion) -> Vec<Obstacle>;
 }
 
 impl AsObstacle for Collider {
-    fn to_obstacle(&self) -> Option<Obstacle> {
-        let shape = self.shape_scaled().as_typed_shape();
-        match shape {
-            /*TypedShape::Cuboid(cuboid) => {
-                let [tr, tl, bl, br] = rect_inner(Vec3::from(cuboid.half_extents * 2.0));
-
-                Some(Obstacle::Closed {
-                    vertices: vec![tr, tl, bl, br],
-                })
-            }
+    /// The obstacles of the unscaled shape, in local space. The scale of the entity is
+    /// applied along with the rest of its [`Transform`] by
+    /// [`transform_points`](TransformObstacle::transform_points), since avian already
+    /// copies it into [`shape_scaled`](Collider::shape_scaled).
+    fn to_obstacles(&self, conversion: &ObstacleConversion) -> Vec<Obstacle> {
+        shape_obstacles(self.shape().as_typed_shape(), conversion)
+    }
+}
 
-            TypedShape::Triangle(tri) => Some(Obstacle::Closed {
-                vertices: vec![tri.a.xz().into(), tri.b.xz().into(), tri.c.xz().into()],
-            }),
-
-            TypedShape::Cylinder(cylinder) => {
-                let num_agents = 12; // TODO configuration resource
-                Some(Obstacle::Closed {
-                    vertices: (0..num_agents)
-                        .map(|i| {
-                            let theta =
-                                2.0 * std::f32::consts::PI * (i as f32) / (num_agents as f32);
-                            point_on_circle((0., 0.), cylinder.radius, theta)
-                        })
-                        .collect(),
-                })
-            }
+/// Converts a shape into obstacles in its local space, with closed obstacles
+/// counter-clockwise.
+fn shape_obstacles(shape: TypedShape, conversion: &ObstacleConversion) -> Vec<Obstacle> {
+    let point = |p: &Point<f32>| Vec2::new(p.x, p.y);
+    let rounded = |vertices: &[Vec2], radius: f32| {
+        if radius > 0.0 {
+            rounded_convex(vertices, radius, conversion.circle_segments)
+        } else {
+            vertices.to_vec()
+        }
+    };
+    let closed = |vertices: Vec<Vec2>| {
+        let mut obstacle = Obstacle::Closed { vertices };
+        correct_winding(&mut obstacle, Winding::CounterClockwise);
+        obstacle
+    };
 
-            TypedShape::Capsule(capsule) => {
-                let num_agents = 12; // TODO configuration resource
-                Some(Obstacle::Closed {
-                    vertices: (0..num_agents)
-                        .map(|i| {
-                            let theta =
-                                2.0 * std::f32::consts::PI * (i as f32) / (num_agents as f32);
-                            point_on_circle((0., 0.), capsule.radius, theta)
-                        })
-                        .collect(),
-                })
+    match shape {
+        TypedShape::Cuboid(cuboid) => {
+            let [tr, tl, bl, br] = rect_inner(Vec3::new(
+                cuboid.half_extents.x * 2.0,
+                0.0,
+                cuboid.half_extents.y * 2.0,
+            ));
+            vec![closed(vec![tr, tl, bl, br])]
+        }
+
+        TypedShape::RoundCuboid(round) => {
+            let half_extents = round.inner_shape.half_extents;
+            let [tr, tl, bl, br] =
+                rect_inner(Vec3::new(half_extents.x * 2.0, 0.0, half_extents.y * 2.0));
+            vec![closed(rounded(&[tr, tl, bl, br], round.border_radius))]
+        }
+
+        TypedShape::Ball(ball) => vec![closed(rounded(&[Vec2::ZERO], ball.radius))],
+
+        TypedShape::Capsule(capsule) => {
+            let segment = [point(&capsule.segment.a), point(&capsule.segment.b)];
+            vec![closed(rounded(&segment, capsule.radius))]
+        }
+
+        TypedShape::Triangle(tri) => {
+            vec![closed(vec![point(&tri.a), point(&tri.b), point(&tri.c)])]
+        }
+
+        TypedShape::RoundTriangle(round) => {
+            let tri = &round.inner_shape;
+            let mut vertices = vec![point(&tri.a), point(&tri.b), point(&tri.c)];
+            if signed_area(&vertices) < 0.0 {
+                vertices.reverse();
             }
+            vec![closed(rounded(&vertices, round.border_radius))]
+        }
 
-            TypedShape::Ball(ball) => {
-                let num_agents = 12; // TODO configuration resource
-                Some(Obstacle::Closed {
-                    vertices: (0..num_agents)
-                        .map(|i| {
-                            let theta =
-                                2.0 * std::f32::consts::PI * (i as f32) / (num_agents as f32);
-                            point_on_circle((0., 0.), ball.radius, theta)
-                        })
-                        .collect(),
-                })
+        TypedShape::ConvexPolygon(polygon) => {
+            vec![closed(polygon.points().iter().map(point).collect())]
+        }
+
+        TypedShape::RoundConvexPolygon(round) => {
+            let vertices: Vec<Vec2> = round.inner_shape.points().iter().map(point).collect();
+            vec![closed(rounded(&vertices, round.border_radius))]
+        }
+
+        TypedShape::Segment(segment) => vec![Obstacle::Open {
+            vertices: vec![point(&segment.a), point(&segment.b)],
+        }],
+
+        TypedShape::Polyline(polyline) => {
+            // Chain consecutive segments into as few open obstacles as possible.
+            let vertices = polyline.vertices();
+            let mut chains: Vec<(u32, Vec<Vec2>)> = vec![];
+            for [a, b] in polyline.indices() {
+                match chains.last_mut() {
+                    Some((end, chain)) if *end == *a => {
+                        chain.push(point(&vertices[*b as usize]));
+                        *end = *b;
+                    }
+                    _ => chains.push((
+                        *b,
+                        vec![point(&vertices[*a as usize]), point(&vertices[*b as usize])],
+                    )),
+                }
             }
+            chains
+                .into_iter()
+                .map(|(_, vertices)| Obstacle::Open { vertices })
+                .collect()
+        }
 
-            TypedShape::Cone(cone) => {
-                let num_agents = 12; // TODO configuration resource
-                Some(Obstacle::Closed {
-                    vertices: (0..num_agents)
-                        .map(|i| {
-                            let theta =
-                                2.0 * std::f32::consts::PI * (i as f32) / (num_agents as f32);
-                            point_on_circle((0., 0.), cone.radius, theta)
-                        })
-                        .collect(),
+        TypedShape::TriMesh(mesh) => {
+            let vertices = mesh.vertices();
+            let triangles: Vec<Vec<Vec2>> = mesh
+                .indices()
+                .iter()
+                .map(|triangle| {
+                    let mut triangle: Vec<Vec2> = triangle
+                        .iter()
+                        .map(|i| point(&vertices[*i as usize]))
+                        .collect();
+                    if signed_area(&triangle) < 0.0 {
+                        triangle.reverse();
+                    }
+                    triangle
                 })
-            }*/
+                .collect();
+            merge_shared_edges(&triangles)
+                .into_iter()
+                .map(|vertices| Obstacle::Closed { vertices })
+                .collect()
+        }
 
-            _ => {
-                warn_once!("The shape isn't supported.");
-                None
+        TypedShape::Compound(compound) => {
+            let mut closed_parts = vec![];
+            let mut open_parts = vec![];
+            for (isometry, shape) in compound.shapes() {
+                let rotation = Vec2::from_angle(isometry.rotation.angle());
+                let translation = Vec2::new(isometry.translation.x, isometry.translation.y);
+                for mut obstacle in shape_obstacles(shape.as_typed_shape(), conversion) {
+                    match &mut obstacle {
+                        Obstacle::Closed { vertices } | Obstacle::Open { vertices } => {
+                            for vertex in vertices.iter_mut() {
+                                *vertex = translation + rotation.rotate(*vertex);
+                            }
+                        }
+                    }
+                    match obstacle {
+                        Obstacle::Closed { .. } => closed_parts.push(obstacle),
+                        Obstacle::Open { .. } => open_parts.push(obstacle),
+                    }
+                }
             }
+            closed_parts = match conversion.compound_union_cell_size {
+                Some(cell_size) => union_obstacles(&closed_parts, cell_size),
+                // Parts of a convex decomposition share their edges and merge exactly.
+                None => merge_shared_edges(&polygons(&closed_parts))
+                    .into_iter()
+                    .map(|vertices| Obstacle::Closed { vertices })
+                    .collect(),
+            };
+            closed_parts.extend(open_parts);
+            closed_parts
+        }
+
+        _ => {
+            warn_once!("The shape isn't supported.");
+            vec![]
         }
     }
 }
 
+/// The vertices of closed obstacles.
+fn polygons(obstacles: &[Obstacle]) -> Vec<Vec<Vec2>> {
+    obstacles
+        .iter()
+        .filter_map(|obstacle| match obstacle {
+            Obstacle::Closed { vertices } => Some(vertices.clone()),
+            Obstacle::Open { .. } => None,
+        })
+        .collect()
+}
+
 pub trait TransformObstacle {
     fn transform_points(&mut self, tf: &Transform);
 }
 
 impl TransformObstacle for Obstacle {
+    /// Moves the vertices into the space of `tf`, reversing them when it mirrors so the
+    /// obstacle keeps its winding.
     fn transform_points(&mut self, tf: &Transform) {
+        let mirrored = tf.scale.x * tf.scale.y < 0.0;
         match self {
-            Obstacle::Closed { vertices } => {
+            Obstacle::Closed { vertices } | Obstacle::Open { vertices } => {
                 vertices.iter_mut().for_each(|vec2| {
-                    let pt = Vec3::new(vec2.x, 0., vec2.y);
-                    *vec2 = tf.transform_point(pt).xz();
+                    *vec2 = tf.transform_point(vec2.extend(0.0)).xy();
                 });
+                if mirrored {
+                    vertices.reverse();
+                }
             }
-            Obstacle::Open { vertices } => {
-                vertices.iter_mut().for_each(|vec2| {
-                    let pt = Vec3::new(vec2.x, 0., vec2.y);
-                    *vec2 = tf.transform_point(pt).xz();
-                });
+        }
+    }
+}
+
+/// An obstacle considered by the avoidance without being a physics collider, e.g. an
+/// invisible keep-out zone or a lawn agents should walk around.
+///
+/// The vertices are relative to the entity's [`Transform`]. Closed obstacles list their
+/// vertices counter-clockwise, or clockwise to keep agents inside, like the bounds of a
+/// level.
+#[derive(Component, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Component, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[require(Transform)]
+pub struct DodgyObstacle {
+    pub vertices: Vec<Vec2>,
+
+    /// Whether the last vertex connects back to the first.
+    pub closed: bool,
+}
+
+impl DodgyObstacle {
+    /// A rectangle of `size` centred on the entity.
+    pub fn rect(size: Vec2) -> DodgyObstacle {
+        DodgyObstacle::polygon(rect_inner(Vec3::new(size.x, 0.0, size.y)).to_vec())
+    }
+
+    /// A closed polygon.
+    pub fn polygon(vertices: impl Into<Vec<Vec2>>) -> DodgyObstacle {
+        DodgyObstacle {
+            vertices: vertices.into(),
+            closed: true,
+        }
+    }
+
+    /// An open chain of segments, like a fence agents can walk around.
+    pub fn polyline(vertices: impl Into<Vec<Vec2>>) -> DodgyObstacle {
+        DodgyObstacle {
+            vertices: vertices.into(),
+            closed: false,
+        }
+    }
+
+    /// The obstacle in local space.
+    pub fn to_obstacle(&self) -> Obstacle {
+        let vertices = self.vertices.clone();
+        if self.closed {
+            Obstacle::Closed { vertices }
+        } else {
+            Obstacle::Open { vertices }
+        }
+    }
+}
+
+impl From<Obstacle> for DodgyObstacle {
+    fn from(obstacle: Obstacle) -> Self {
+        match obstacle {
+            Obstacle::Closed { vertices } => DodgyObstacle::polygon(vertices),
+            Obstacle::Open { vertices } => DodgyObstacle::polyline(vertices),
+        }
+    }
+}
+
+/// A uniform grid of the [`DodgyObstacle`]s in world space, used by the avoidance to find
+/// the obstacles near an agent.
+#[derive(Resource, Clone, Debug)]
+pub struct DodgyObstacleIndex {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+    entries: EntityHashMap<IndexedObstacle>,
+}
+
+/// A [`DodgyObstacle`] stored in the [`DodgyObstacleIndex`].
+#[derive(Clone, Debug)]
+pub struct IndexedObstacle {
+    /// The obstacle in world space.
+    pub obstacle: Obstacle,
+
+    /// The ordering key of the obstacle in [`AvoidanceMode::Deterministic`](crate::AvoidanceMode).
+    pub key: u64,
+
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Default for DodgyObstacleIndex {
+    fn default() -> Self {
+        DodgyObstacleIndex::new(128.0)
+    }
+}
+
+impl DodgyObstacleIndex {
+    /// Creates an empty index whose grid cells are `cell_size` wide.
+    pub fn new(cell_size: f32) -> DodgyObstacleIndex {
+        DodgyObstacleIndex {
+            cell_size,
+            cells: HashMap::default(),
+            entries: EntityHashMap::default(),
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&IndexedObstacle> {
+        self.entries.get(&entity)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &IndexedObstacle)> {
+        self.entries.iter().map(|(entity, entry)| (*entity, entry))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The obstacles whose bounds overlap the circle.
+    pub fn query_circle(&self, center: Vec2, radius: f32) -> Vec<(Entity, &IndexedObstacle)> {
+        let mut found = EntityHashSet::default();
+        let mut result = vec![];
+        for cell in self.cells_between(center - radius, center + radius) {
+            for entity in self.cells.get(&cell).into_iter().flatten() {
+                if !found.insert(*entity) {
+                    continue;
+                }
+                let entry = &self.entries[entity];
+                if center.clamp(entry.min, entry.max).distance_squared(center) <= radius * radius {
+                    result.push((*entity, entry));
+                }
+            }
+        }
+        result
+    }
+
+    pub(crate) fn insert(&mut self, entity: Entity, key: u64, obstacle: Obstacle) {
+        self.remove(entity);
+
+        let vertices = match &obstacle {
+            Obstacle::Closed { vertices } | Obstacle::Open { vertices } => vertices,
+        };
+        let min = vertices
+            .iter()
+            .copied()
+            .reduce(Vec2::min)
+            .unwrap_or_default();
+        let max = vertices
+            .iter()
+            .copied()
+            .reduce(Vec2::max)
+            .unwrap_or_default();
+        for cell in self.cells_between(min, max) {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+        self.entries.insert(
+            entity,
+            IndexedObstacle {
+                obstacle,
+                key,
+                min,
+                max,
+            },
+        );
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        let Some(entry) = self.entries.remove(&entity) else {
+            return;
+        };
+        for cell in self.cells_between(entry.min, entry.max) {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|e| *e != entity);
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
             }
         }
     }
+
+    fn cells_between(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = IVec2> {
+        let min = (min / self.cell_size).floor().as_ivec2();
+        let max = (max / self.cell_size).floor().as_ivec2();
+        (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+    }
+}
+
+/// Keeps the [`DodgyObstacleIndex`] in sync with the [`DodgyObstacle`]s and their transforms.
+pub(crate) fn update_obstacle_index(
+    mut index: ResMut<DodgyObstacleIndex>,
+    obstacles: Query<
+        (Entity, &Transform, &DodgyObstacle, Option<&StableId>),
+        Or<(
+            Changed<Transform>,
+            Changed<DodgyObstacle>,
+            Changed<StableId>,
+        )>,
+    >,
+    mut removed: RemovedComponents<DodgyObstacle>,
+) {
+    for entity in removed.read() {
+        index.remove(entity);
+    }
+    for (entity, tf, obstacle, stable_id) in obstacles.iter() {
+        let mut world_obstacle = obstacle.to_obstacle();
+        world_obstacle.transform_points(tf);
+        index.insert(entity, StableId::key(entity, stable_id), world_obstacle);
+    }
 }
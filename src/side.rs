@@ -0,0 +1,90 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use dodgy_2d::Agent;
+
+/// Makes an agent consistently pass others on one side in head-on encounters.
+///
+/// When a neighbour approaches along a nearly collinear path, the preferred velocity is
+/// rotated toward the preferred side, which breaks the symmetry that makes opposing
+/// agents stall and leads to natural lane formation.
+#[derive(Component, Reflect, Clone, Copy, PartialEq, Eq, Debug)]
+#[reflect(Component, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SidePreference {
+    KeepRight,
+    KeepLeft,
+    /// Uses the side of the culture in [`SidePreferenceConfig::cultures`], keeping right
+    /// when the culture is unknown.
+    Culture(u32),
+}
+
+/// Tunes the bias applied by [`SidePreference`].
+#[derive(Resource, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SidePreferenceConfig {
+    /// The largest rotation applied to the preferred velocity, in radians.
+    pub max_bias_angle: f32,
+
+    /// The cosine of the angle between the relative velocity and the direction to a
+    /// neighbour above which the encounter counts as head-on.
+    pub head_on_cosine: f32,
+
+    /// The side kept by each culture of [`SidePreference::Culture`].
+    pub cultures: HashMap<u32, SidePreference>,
+}
+
+impl Default for SidePreferenceConfig {
+    fn default() -> Self {
+        Self {
+            max_bias_angle: 0.35,
+            head_on_cosine: 0.9,
+            cultures: HashMap::default(),
+        }
+    }
+}
+
+impl SidePreferenceConfig {
+    /// The rotation direction of a preference: negative (clockwise) to keep right.
+    fn sign(&self, preference: SidePreference) -> f32 {
+        match preference {
+            SidePreference::KeepRight => -1.0,
+            SidePreference::KeepLeft => 1.0,
+            SidePreference::Culture(culture) => match self.cultures.get(&culture) {
+                Some(SidePreference::KeepLeft) => 1.0,
+                _ => -1.0,
+            },
+        }
+    }
+
+    /// Rotates `preferred_velocity` toward the preferred side, proportionally to how
+    /// head-on the most head-on approaching neighbour is.
+    pub fn bias_preferred_velocity(
+        &self,
+        preference: SidePreference,
+        agent: &Agent,
+        neighbours: &[&Agent],
+        preferred_velocity: Vec2,
+    ) -> Vec2 {
+        let head_on = neighbours
+            .iter()
+            .filter_map(|neighbour| {
+                let relative_position = neighbour.position - agent.position;
+                let relative_velocity = preferred_velocity - neighbour.velocity;
+                let cosine = relative_velocity
+                    .normalize_or_zero()
+                    .dot(relative_position.normalize_or_zero());
+
+                (cosine > self.head_on_cosine)
+                    .then(|| (cosine - self.head_on_cosine) / (1.0 - self.head_on_cosine))
+            })
+            .fold(0.0, f32::max);
+
+        if head_on <= 0.0 {
+            return preferred_velocity;
+        }
+
+        let angle = self.sign(preference) * self.max_bias_angle * head_on.min(1.0);
+        Vec2::from_angle(angle).rotate(preferred_velocity)
+    }
+}
@@ -0,0 +1,110 @@
+use crate::agents::{AgentGoal, AgentInfo};
+use crate::obstacles::{DodgyObstacle, DodgyObstacleIndex};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+
+/// Marks an agent whose avoidance is suspended, analogous to avian's `Sleeping`.
+///
+/// Sleeping agents are skipped by the avoidance solve entirely. They wake up when a
+/// moving agent comes near, when their [`AgentGoal`] changes, when an obstacle moves
+/// close to them, or when something pushes them.
+#[derive(Component, Reflect, Clone, Copy, Default, Debug)]
+#[reflect(Component, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AgentSleeping;
+
+/// The time an agent has spent idle with idle neighbours, in seconds.
+#[derive(Component, Reflect, Clone, Copy, Default, PartialEq, Debug, Deref, DerefMut)]
+#[reflect(Component, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AgentSleepTimer(pub f32);
+
+/// Controls when agents fall asleep.
+#[derive(Resource, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Resource, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AgentSleepConfig {
+    /// Whether agents are allowed to sleep at all.
+    pub enabled: bool,
+
+    /// Agents and neighbours moving slower than this are considered idle.
+    pub linear_threshold: f32,
+
+    /// How long an agent must stay idle before falling asleep, in seconds.
+    pub time_to_sleep: f32,
+
+    /// Distance around a moving obstacle within which sleeping agents are woken up.
+    pub obstacle_wake_distance: f32,
+}
+
+impl Default for AgentSleepConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            linear_threshold: 0.5,
+            time_to_sleep: 0.5,
+            obstacle_wake_distance: 50.0,
+        }
+    }
+}
+
+/// Wakes sleeping agents whose goal changed or that were pushed by something else.
+pub(crate) fn wake_disturbed_agents(
+    mut commands: Commands,
+    query: Query<
+        (Entity, &LinearVelocity, Option<Ref<AgentGoal>>),
+        (With<AgentSleeping>, With<AgentInfo>),
+    >,
+    mut removed_goals: RemovedComponents<AgentGoal>,
+    config: Res<AgentSleepConfig>,
+) {
+    for (entity, linvel, goal) in query.iter() {
+        let goal_changed = goal.is_some_and(|goal| goal.is_changed());
+        if goal_changed || linvel.length() > config.linear_threshold {
+            commands.entity(entity).remove::<AgentSleeping>();
+        }
+    }
+
+    for entity in removed_goals.read() {
+        if query.contains(entity) {
+            commands.entity(entity).remove::<AgentSleeping>();
+        }
+    }
+}
+
+/// Wakes sleeping agents around obstacles whose transform or shape changed.
+pub(crate) fn wake_agents_near_moved_obstacles(
+    mut commands: Commands,
+    obstacles: Query<(&Transform, &Collider), (Changed<Transform>, Without<AgentInfo>)>,
+    dodgy_obstacles: Query<
+        Entity,
+        (
+            With<DodgyObstacle>,
+            Or<(Changed<Transform>, Changed<DodgyObstacle>)>,
+        ),
+    >,
+    index: Res<DodgyObstacleIndex>,
+    sleeping: Query<(), With<AgentSleeping>>,
+    spatial: SpatialQuery,
+    config: Res<AgentSleepConfig>,
+) {
+    let collider_aabbs = obstacles
+        .iter()
+        .map(|(tf, collider)| collider.aabb(tf.translation.xy(), tf.rotation));
+    let dodgy_obstacle_aabbs = dodgy_obstacles
+        .iter()
+        .filter_map(|entity| index.get(entity))
+        .map(|entry| ColliderAabb {
+            min: entry.min,
+            max: entry.max,
+        });
+
+    for aabb in collider_aabbs.chain(dodgy_obstacle_aabbs) {
+        let aabb = aabb.grow(Vec2::splat(config.obstacle_wake_distance));
+        for entity in spatial.aabb_intersections_with_aabb(aabb) {
+            if sleeping.contains(entity) {
+                commands.entity(entity).remove::<AgentSleeping>();
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use crate::agents::{AgentGoal, AgentInfo, StableId};
+use bevy::prelude::*;
+
+/// Detects agents that stop making progress toward their goal.
+///
+/// Every [`window`](Self::window) seconds, the agent must have come at least
+/// [`min_progress`](Self::min_progress) closer to its goal. Otherwise an [`AgentStuck`]
+/// event is sent and the [`recovery`](Self::recovery) strategy is applied.
+///
+/// Measuring restarts when the goal jumps further than its tolerance in a single tick, so
+/// goals that move steadily, like the slots of a [`Formation`](crate::formations::Formation),
+/// are still checked.
+#[derive(Component, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Component, Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StuckDetector {
+    /// The duration over which progress is measured, in seconds.
+    pub window: f32,
+
+    /// The distance the agent must get closer to its goal within a window.
+    pub min_progress: f32,
+
+    /// What to do once the agent is stuck.
+    pub recovery: StuckRecovery,
+
+    elapsed: f32,
+    start_distance: Option<f32>,
+    last_goal: Option<Vec2>,
+}
+
+impl Default for StuckDetector {
+    fn default() -> Self {
+        StuckDetector::new(2.0, 5.0)
+    }
+}
+
+impl StuckDetector {
+    pub fn new(window: f32, min_progress: f32) -> StuckDetector {
+        StuckDetector {
+            window,
+            min_progress,
+            recovery: StuckRecovery::default(),
+            elapsed: 0.0,
+            start_distance: None,
+            last_goal: None,
+        }
+    }
+
+    pub fn with_recovery(mut self, recovery: StuckRecovery) -> StuckDetector {
+        self.recovery = recovery;
+        self
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = 0.0;
+        self.start_distance = None;
+    }
+}
+
+/// The recovery applied to a stuck agent.
+#[derive(Reflect, Clone, Copy, PartialEq, Debug, Default)]
+#[reflect(Default, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StuckRecovery {
+    /// Only send the [`AgentStuck`] event, e.g. to let gameplay replan.
+    #[default]
+    None,
+
+    /// Adds a pseudo-random perturbation of `strength` times the max speed to the
+    /// preferred velocity for `duration` seconds.
+    Perturb { strength: f32, duration: f32 },
+
+    /// Multiplies the avoidance responsibility of the agent by `factor` for `duration`
+    /// seconds, so it gets out of the way of its neighbours.
+    BoostResponsibility { factor: f32, duration: f32 },
+
+    /// Heads to a temporary waypoint `offset` to the side of the direct path for up to
+    /// `duration` seconds before resuming the goal.
+    Replan { offset: f32, duration: f32 },
+}
+
+/// Sent when a [`StuckDetector`] finds its agent made too little progress toward its goal.
+#[derive(Event, Clone, Copy, PartialEq, Debug)]
+pub struct AgentStuck {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub goal: Vec2,
+}
+
+/// The recovery currently applied to a stuck agent, consumed by the avoidance.
+#[derive(Component, Reflect, Clone, PartialEq, Debug)]
+#[reflect(Component, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StuckRecoveryState {
+    /// Seconds left before the recovery ends.
+    pub remaining: f32,
+
+    /// Added to the preferred velocity.
+    pub perturbation: Vec2,
+
+    /// Multiplies the avoidance responsibility of the agent.
+    pub responsibility_factor: f32,
+
+    /// Replaces the goal as the destination while set.
+    pub waypoint: Option<Vec2>,
+}
+
+impl StuckRecoveryState {
+    /// Applies the recovery to the preferred velocity of an agent at `position`.
+    pub fn preferred_velocity(
+        &self,
+        preferred_velocity: Vec2,
+        position: Vec2,
+        max_speed: f32,
+    ) -> Vec2 {
+        let preferred_velocity = match self.waypoint {
+            Some(waypoint) => (waypoint - position).normalize_or_zero() * max_speed,
+            None => preferred_velocity,
+        };
+        (preferred_velocity + self.perturbation).clamp_length_max(max_speed)
+    }
+}
+
+pub(crate) fn detect_stuck_agents(
+    mut commands: Commands,
+    mut query: Query<(
+        Entity,
+        &Transform,
+        &AgentInfo,
+        &AgentGoal,
+        &mut StuckDetector,
+        Option<&mut StuckRecoveryState>,
+        Option<&StableId>,
+    )>,
+    mut events: EventWriter<AgentStuck>,
+    time: Res<Time>,
+) {
+    let delta_secs = time.delta_secs();
+
+    for (entity, tf, info, goal, mut detector, recovery_state, stable_id) in query.iter_mut() {
+        let position = tf.translation.xy();
+        let goal_moved = !detector
+            .last_goal
+            .replace(goal.dest)
+            .is_some_and(|last_goal| last_goal.distance(goal.dest) <= goal.tolerance);
+
+        if let Some(mut recovery_state) = recovery_state {
+            recovery_state.remaining -= delta_secs;
+            let reached_waypoint = recovery_state
+                .waypoint
+                .is_some_and(|waypoint| position.distance(waypoint) <= info.radius);
+            if recovery_state.remaining <= 0.0 || reached_waypoint || goal_moved {
+                commands.entity(entity).remove::<StuckRecoveryState>();
+            }
+            // Progress isn't measured while recovering.
+            detector.reset();
+            continue;
+        }
+
+        let distance = position.distance(goal.dest);
+        if goal_moved || distance <= goal.tolerance {
+            detector.reset();
+            continue;
+        }
+
+        let start_distance = *detector.start_distance.get_or_insert(distance);
+        detector.elapsed += delta_secs;
+        if detector.elapsed < detector.window {
+            continue;
+        }
+        detector.reset();
+
+        if start_distance - distance >= detector.min_progress {
+            continue;
+        }
+
+        events.send(AgentStuck {
+            entity,
+            position,
+            goal: goal.dest,
+        });
+
+        let seed = StableId::key(entity, stable_id) ^ time.elapsed().as_nanos() as u64;
+        if let Some(recovery_state) =
+            start_recovery(detector.recovery, position, goal.dest, info.max_speed, seed)
+        {
+            commands.entity(entity).insert(recovery_state);
+        }
+    }
+}
+
+fn start_recovery(
+    recovery: StuckRecovery,
+    position: Vec2,
+    goal: Vec2,
+    max_speed: f32,
+    seed: u64,
+) -> Option<StuckRecoveryState> {
+    let neutral = StuckRecoveryState {
+        remaining: 0.0,
+        perturbation: Vec2::ZERO,
+        responsibility_factor: 1.0,
+        waypoint: None,
+    };
+
+    match recovery {
+        StuckRecovery::None => None,
+        StuckRecovery::Perturb { strength, duration } => Some(StuckRecoveryState {
+            remaining: duration,
+            perturbation: pseudo_random_direction(seed) * strength * max_speed,
+            ..neutral
+        }),
+        StuckRecovery::BoostResponsibility { factor, duration } => Some(StuckRecoveryState {
+            remaining: duration,
+            responsibility_factor: factor,
+            ..neutral
+        }),
+        StuckRecovery::Replan { offset, duration } => {
+            let side = if seed & 1 == 0 { 1.0 } else { -1.0 };
+            let sideways = (goal - position).normalize_or_zero().perp() * side;
+            Some(StuckRecoveryState {
+                remaining: duration,
+                waypoint: Some(position + sideways * offset),
+                ..neutral
+            })
+        }
+    }
+}
+
+/// A unit vector derived deterministically from `seed`.
+fn pseudo_random_direction(seed: u64) -> Vec2 {
+    // SplitMix64 finaliser.
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+
+    let theta = (z >> 40) as f32 / (1u64 << 24) as f32 * std::f32::consts::TAU;
+    Vec2::from_angle(theta)
+}
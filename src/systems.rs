@@ -1,114 +1,493 @@
-use crate::agents::{AgentInfo, AgentQueryData, AgentQueryDataMut};
-use crate::obstacles::{AsObstacle, TransformObstacle};
+use crate::agents::{
+    AgentInfo, AgentQueryData, AgentQueryDataMut, AgentQueryDataMutItem, AvoidanceNeighbours,
+    AvoidanceResult, StableId,
+};
+use crate::constraints::AvoidanceConstraints;
+use crate::diagnostics::AvoidanceStats;
+use crate::geometry::{cull_obstacle, sweep_obstacle};
+use crate::groups::{AvoidanceGroup, GroupPolicy};
+use crate::inflation::{InflatedObstacles, ObstacleInflation};
+use crate::lod::{apply_budget, AvoidanceFocus, AvoidanceLod, LodCandidate};
+use crate::motion::{ObstacleMotion, ObstacleVelocity};
+use crate::obstacles::{AsObstacle, DodgyObstacleIndex, ObstacleConversion, TransformObstacle};
+use crate::side::SidePreferenceConfig;
+use crate::sleep::{AgentSleepConfig, AgentSleeping};
+use crate::AvoidanceMode;
 use avian2d::prelude::*;
+use bevy::ecs::entity::EntityHashMap;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::utils::Instant;
 use dodgy_2d::{Agent, Obstacle};
 use std::borrow::Cow;
 
 pub fn rvo_avoidance(
+    mut commands: Commands,
     agents: Query<AgentQueryData>,
     mut query: Query<(AgentQueryDataMut, &RigidBody)>,
-    q_obstacles: Query<(&Transform, &Collider, &RigidBody), Without<AgentInfo>>,
+    q_obstacles: Query<(&Transform, &Collider, &RigidBody, Option<&StableId>), Without<AgentInfo>>,
+    q_inflated: Query<&InflatedObstacles>,
+    q_moving: Query<(&ObstacleVelocity, &Transform), Without<AgentInfo>>,
+    q_foci: Query<
+        (&GlobalTransform, Has<AvoidanceFocus>),
+        Or<(With<Camera>, With<AvoidanceFocus>)>,
+    >,
     spatial: SpatialQuery,
+    settings: AvoidanceSettings,
+    mut stats: ResMut<AvoidanceStats>,
+    mut tick: Local<u32>,
     time: Res<Time>,
 ) {
     if !(time.delta_secs() > 0.0) {
         return;
     }
+    let start = Instant::now();
+    stats.passes += 1;
+    let AvoidanceSettings {
+        mode,
+        lod,
+        sleep_config,
+        side_config,
+        groups,
+        obstacle_index,
+        conversion,
+        inflation,
+        motion,
+    } = settings;
+    let deterministic = *mode == AvoidanceMode::Deterministic;
+    *tick = tick.wrapping_add(1);
+
+    // Snapshot every agent before solving so that velocities written during this
+    // pass never leak into the neighbourhood of agents solved later in the same pass.
+    let snapshot: EntityHashMap<AgentSnapshot> = query
+        .iter()
+        .map(|(data, body)| {
+            let snapshot = AgentSnapshot {
+                agent: Agent::from(&data),
+                key: StableId::key(data.entity, data.stable_id),
+                dynamic: body.is_dynamic(),
+                solved_tick: data.result.solved_tick,
+                sleeping: data.sleeping,
+                records_neighbours: data.neighbours.is_some(),
+                records_constraints: data.constraints.is_some(),
+                group: data.group.copied(),
+            };
+            (data.entity, snapshot)
+        })
+        .collect();
+
+    // Select the agents solved this tick; the others reuse their last result. Cameras are
+    // local to each peer, so only explicit foci count in deterministic mode.
+    let foci: Vec<Vec2> = q_foci
+        .iter()
+        .filter(|(_, is_focus)| *is_focus || !deterministic)
+        .map(|(tf, _)| tf.translation().xy())
+        .collect();
+    let mut candidates: Vec<LodCandidate<_>> = agents
+        .iter()
+        .filter(|agent_data| !agent_data.sleeping)
+        .filter_map(|agent_data| {
+            let agent_snapshot = snapshot.get(&agent_data.entity)?;
+            let near = lod.is_near(agent_data.transform.translation.xy(), &foci);
+            if !near && !lod.is_due(agent_snapshot.key, *tick, agent_snapshot.solved_tick) {
+                return None;
+            }
+            Some(LodCandidate {
+                item: agent_data,
+                near,
+                last_solved_tick: agent_snapshot.solved_tick,
+            })
+        })
+        .collect();
+    if deterministic {
+        candidates.sort_by_key(|candidate| candidate.item.sort_key());
+    }
+    apply_budget(&mut candidates, lod.agent_budget, *tick);
 
-    for agent_data in agents.iter() {
-        let (agent_data, _) = query.get(agent_data.entity).unwrap();
-        let dodgy_agent = Agent::from(&agent_data);
+    let mut results: EntityHashMap<SolveOutcome> = EntityHashMap::default();
+    let mut woken: Vec<Entity> = vec![];
+    for LodCandidate {
+        item: agent_data, ..
+    } in candidates
+    {
+        let Some(agent_snapshot) = snapshot.get(&agent_data.entity) else {
+            continue;
+        };
+        let dodgy_agent = &agent_snapshot.agent;
 
+        let query_radius =
+            agent_data.info.radius + agent_data.options.time_horizon * agent_data.info.max_speed;
         let intersections = spatial.shape_intersections(
-            &Collider::circle(
-                agent_data.info.radius
-                    + agent_data.options.time_horizon * agent_data.info.max_speed,
-            ),
+            &Collider::circle(query_radius),
             agent_data.transform.translation.xy(),
             0.0,
             &SpatialQueryFilter::default().with_excluded_entities([agent_data.entity]), // Exclude self
         );
 
-        // Filter the intersected entities to return only dynamic agents
-        let neighbours: Vec<Cow<'static, Agent>> = intersections
-            .clone()
-            .into_iter()
+        // Filter the intersected entities to return only dynamic agents, as seen through
+        // the group policy.
+        let mut neighbours: Vec<(u64, Entity, Cow<'static, Agent>)> = intersections
+            .iter()
             .filter_map(|e| {
-                if let Ok((data, body)) = query.get(e) {
-                    if body.is_dynamic() {
-                        return Some(Cow::Owned(Agent::from(&data)));
-                    }
-                };
-                None
+                let neighbour = snapshot.get(e).filter(|neighbour| neighbour.dynamic)?;
+                let agent = groups.apply(
+                    dodgy_agent,
+                    agent_snapshot.group,
+                    &neighbour.agent,
+                    neighbour.group,
+                )?;
+                Some((neighbour.key, *e, Cow::Owned(agent)))
             })
             .collect();
+        if deterministic {
+            neighbours.sort_by_key(|(key, _, _)| *key);
+        }
+        let (neighbour_entities, neighbours): (Vec<Entity>, Vec<Cow<'static, Agent>>) = neighbours
+            .into_iter()
+            .map(|(_, e, agent)| (e, agent))
+            .unzip();
 
-        // If the agent has no goal, ignore.
-        let Some(agent_goal) = agent_data.goal else {
+        // The agent may fall asleep once it and its neighbours stop moving.
+        let idle = dodgy_agent.velocity.length() <= sleep_config.linear_threshold
+            && neighbours
+                .iter()
+                .all(|neighbour| neighbour.velocity.length() <= sleep_config.linear_threshold);
+
+        // If the agent has no goal or is within the goal tolerance, ignore.
+        let position = agent_data.transform.translation.xy();
+        let Some(agent_goal) = agent_data
+            .goal
+            .filter(|agent_goal| (agent_goal.dest - position).length() > agent_goal.tolerance)
+        else {
+            let neighbourhood = agent_snapshot
+                .records_neighbours
+                .then(|| AvoidanceNeighbours {
+                    query_radius,
+                    ..default()
+                });
+            results.insert(
+                agent_data.entity,
+                SolveOutcome {
+                    velocities: None,
+                    arrived: agent_data.goal.is_some(),
+                    idle,
+                    neighbourhood,
+                    constraints: agent_snapshot
+                        .records_constraints
+                        .then(AvoidanceConstraints::default),
+                },
+            );
             continue;
         };
 
-        // Check whether the agent is within the goal tolerance
-        let distance = (agent_goal.dest - agent_data.transform.translation.xy()).length();
-        if distance <= agent_goal.tolerance {
-            continue;
-        }
+        // A moving agent wakes the sleeping agents it is about to avoid.
+        woken.extend(
+            intersections
+                .iter()
+                .copied()
+                .filter(|e| snapshot.get(e).is_some_and(|neighbour| neighbour.sleeping)),
+        );
 
-        let preferred_velocity = (agent_goal.dest - agent_data.transform.translation.xy())
+        let mut preferred_velocity = (agent_goal.dest - agent_data.transform.translation.xy())
             .normalize_or_zero()
             * agent_data.info.max_speed;
+        if let Some(flocking) = agent_data.flocking {
+            let mut flockmates: Vec<(u64, &Agent)> = intersections
+                .iter()
+                .filter_map(|e| snapshot.get(e))
+                .filter(|neighbour| neighbour.group == agent_snapshot.group)
+                .map(|neighbour| (neighbour.key, &neighbour.agent))
+                .collect();
+            if deterministic {
+                flockmates.sort_by_key(|(key, _)| *key);
+            }
+            let flockmates: Vec<&Agent> = flockmates.into_iter().map(|(_, agent)| agent).collect();
+            preferred_velocity = flocking.blend_preferred_velocity(
+                dodgy_agent,
+                &flockmates,
+                preferred_velocity,
+                agent_data.info.max_speed,
+            );
+        }
+        if let Some(side_preference) = agent_data.side_preference {
+            let neighbours: Vec<&Agent> = neighbours.iter().map(AsRef::as_ref).collect();
+            preferred_velocity = side_config.bias_preferred_velocity(
+                *side_preference,
+                dodgy_agent,
+                &neighbours,
+                preferred_velocity,
+            );
+        }
+        if let Some(recovery) = agent_data.recovery {
+            preferred_velocity = recovery.preferred_velocity(
+                preferred_velocity,
+                position,
+                agent_data.info.max_speed,
+            );
+        }
 
-        // Compute the obstacles
-        let mut obstacles: Vec<Cow<'static, Obstacle>> = vec![];
-        for intersect_entity in &intersections {
-            let Ok((obstacle_tf, collider, body)) = q_obstacles.get(*intersect_entity) else {
+        // Compute the obstacles within reach before the obstacle time horizon, using their
+        // variants inflated for the radius class of the agent when they are cached.
+        let obstacle_query_radius = agent_data.info.radius
+            + agent_data.options.obstacle_time_horizon * agent_data.info.max_speed
+            + agent_data.options.obstacle_margin;
+        let obstacle_intersections = spatial.aabb_intersections_with_aabb(ColliderAabb::new(
+            position,
+            Vec2::splat(obstacle_query_radius),
+        ));
+        let class = inflation.class_of(agent_data.info.radius);
+        let inflated = |entity: Entity| {
+            let class = class?;
+            q_inflated.get(entity).ok()?.classes.get(class)
+        };
+        let mut found: Vec<(u64, Entity, Cow<Obstacle>)> = vec![];
+        for intersect_entity in &obstacle_intersections {
+            let Ok((obstacle_tf, collider, body, stable_id)) = q_obstacles.get(*intersect_entity)
+            else {
                 continue;
             };
 
-            // Only static bodies are considered for obstacles.
+            // Only static and kinematic bodies are considered for obstacles.
             match body {
                 RigidBody::Dynamic => {
                     /* Ignore rigid bodies. */
                     warn_once!("Dynamic bodies obstacles are not supported.");
                 }
-                RigidBody::Static => {
-                    if let Some(mut obstacle) = collider.to_obstacle() {
+                RigidBody::Static | RigidBody::Kinematic => {
+                    let key = StableId::key(*intersect_entity, stable_id);
+                    if let Some(inflated) = inflated(*intersect_entity) {
+                        found.extend(
+                            inflated
+                                .iter()
+                                .map(|obstacle| (key, *intersect_entity, Cow::Borrowed(obstacle))),
+                        );
+                        continue;
+                    }
+                    for mut obstacle in collider.to_obstacles(&conversion) {
                         obstacle.transform_points(obstacle_tf);
-                        obstacles.push(Cow::Owned(obstacle));
+                        found.push((key, *intersect_entity, Cow::Owned(obstacle)));
                     }
                 }
-                RigidBody::Kinematic => {
-                    /* Ignore kinematic bodies. */
-                    warn_once!("Kinematic bodies obstacles are not supported.");
+            }
+        }
+        for (entity, entry) in obstacle_index.query_circle(position, obstacle_query_radius) {
+            match inflated(entity) {
+                Some(inflated) => found.extend(
+                    inflated
+                        .iter()
+                        .map(|obstacle| (entry.key, entity, Cow::Borrowed(obstacle))),
+                ),
+                None => found.push((entry.key, entity, Cow::Borrowed(&entry.obstacle))),
+            }
+        }
+
+        // Moving obstacles are replaced by the area they sweep in the near future, and the
+        // edges out of reach are dropped so large obstacles only bring their nearby part.
+        let mut obstacles: Vec<(u64, Entity, Cow<Obstacle>)> = Vec::with_capacity(found.len());
+        for (key, entity, obstacle) in found {
+            let placed = match q_moving.get(entity) {
+                Ok((velocity, tf)) if velocity.is_moving() => sweep_obstacle(
+                    &obstacle,
+                    tf.translation.xy(),
+                    velocity.linear,
+                    velocity.angular,
+                    motion.lookahead,
+                    motion.samples,
+                )
+                .into_iter()
+                .map(Cow::Owned)
+                .collect(),
+                _ => vec![obstacle],
+            };
+            for obstacle in placed {
+                match cull_obstacle(&obstacle, position, obstacle_query_radius) {
+                    Some(parts) => obstacles.extend(
+                        parts
+                            .into_iter()
+                            .map(|part| (key, entity, Cow::Owned(part))),
+                    ),
+                    None => obstacles.push((key, entity, obstacle)),
                 }
             }
         }
+        if deterministic {
+            obstacles.sort_by_key(|(key, _, _)| *key);
+        }
+        let (obstacle_entities, obstacles): (Vec<Entity>, Vec<Cow<Obstacle>>) = obstacles
+            .into_iter()
+            .map(|(_, e, obstacle)| (e, obstacle))
+            .unzip();
+
+        stats.record_agent(neighbours.len(), obstacles.len());
+        let (avoidance_velocity, constraints) = if agent_snapshot.records_constraints {
+            let (velocity, lines, obstacle_line_count) = dodgy_agent
+                .compute_avoiding_velocity_and_lines(
+                    &neighbours,
+                    &obstacles,
+                    preferred_velocity,
+                    agent_data.info.max_speed,
+                    time.delta_secs(),
+                    agent_data.options,
+                );
+            let constraints = AvoidanceConstraints::from_solver_lines(lines, obstacle_line_count);
+            (velocity, Some(constraints))
+        } else {
+            let velocity = dodgy_agent.compute_avoiding_velocity(
+                &neighbours,
+                &obstacles,
+                preferred_velocity,
+                agent_data.info.max_speed,
+                time.delta_secs(),
+                agent_data.options,
+            );
+            (velocity, None)
+        };
 
-        let avoidance_velocity = dodgy_agent.compute_avoiding_velocity(
-            &neighbours,
-            &obstacles,
-            preferred_velocity,
-            agent_data.info.max_speed,
-            time.delta_secs(),
-            agent_data.options,
+        results.insert(
+            agent_data.entity,
+            SolveOutcome {
+                velocities: Some((preferred_velocity, avoidance_velocity)),
+                arrived: false,
+                idle: false,
+                neighbourhood: agent_snapshot
+                    .records_neighbours
+                    .then(|| AvoidanceNeighbours {
+                        query_radius,
+                        obstacle_query_radius,
+                        agents: neighbour_entities,
+                        obstacles: obstacle_entities,
+                    }),
+                constraints,
+            },
         );
+    }
+
+    for entity in woken {
+        commands.entity(entity).remove::<AgentSleeping>();
+    }
+
+    for (mut agent_data_mut, _) in query.iter_mut() {
+        let mut outcome = results.remove(&agent_data_mut.entity);
+        let neighbourhood = outcome
+            .as_mut()
+            .and_then(|outcome| outcome.neighbourhood.take());
+        if let (Some(neighbourhood), Some(neighbours)) =
+            (neighbourhood, agent_data_mut.neighbours.as_mut())
+        {
+            **neighbours = neighbourhood;
+        }
+        let solved_constraints = outcome
+            .as_mut()
+            .and_then(|outcome| outcome.constraints.take());
+        if let (Some(solved_constraints), Some(constraints)) =
+            (solved_constraints, agent_data_mut.constraints.as_mut())
+        {
+            **constraints = solved_constraints;
+        }
 
-        if let Ok((mut agent_data_mut, _)) = query.get_mut(agent_data.entity) {
-            agent_data_mut.linvel.0 = avoidance_velocity;
+        if let Some(outcome) = &outcome {
+            update_sleep_timer(
+                &mut commands,
+                &mut agent_data_mut,
+                outcome.idle,
+                &sleep_config,
+                time.delta_secs(),
+            );
+        }
+
+        match outcome.map(|outcome| (outcome.velocities, outcome.arrived)) {
+            Some((Some((preferred_velocity, velocity)), _)) => {
+                agent_data_mut.linvel.0 = velocity;
+                *agent_data_mut.result = AvoidanceResult {
+                    preferred_velocity,
+                    velocity,
+                    solved_tick: *tick,
+                };
+            }
+            // Nothing to avoid towards. Agents that arrived stop instead of coasting
+            // through their goal, the velocity of agents without a goal is left untouched.
+            Some((None, arrived)) => {
+                if arrived {
+                    agent_data_mut.linvel.0 = Vec2::ZERO;
+                }
+                *agent_data_mut.result = AvoidanceResult {
+                    solved_tick: *tick,
+                    ..default()
+                };
+            }
+            // Skipped by the level of detail, reuse the last result.
+            None if !agent_data_mut.sleeping
+                && agent_data_mut.result.preferred_velocity != Vec2::ZERO =>
+            {
+                agent_data_mut.linvel.0 = agent_data_mut.result.velocity;
+            }
+            None => {}
         }
     }
+
+    stats.solve_time += start.elapsed();
 }
 
-pub(crate) fn on_add_create_collider(
-    mut commands: Commands,
-    query: Query<(Entity, &AgentInfo, Option<&Collider>), Added<AgentInfo>>,
+/// Counts the idle time of a solved agent and puts it to sleep once it has been idle long enough.
+fn update_sleep_timer(
+    commands: &mut Commands,
+    agent: &mut AgentQueryDataMutItem,
+    idle: bool,
+    config: &AgentSleepConfig,
+    delta_secs: f32,
 ) {
-    for (e, agent, option_collider) in query.iter() {
-        if option_collider.is_none() {
-            commands
-                .entity(e)
-                .insert(Collider::circle(agent.radius));
-        }
+    if agent.sleeping {
+        return;
+    }
+    if !config.enabled || !idle {
+        agent.sleep_timer.0 = 0.0;
+        return;
     }
+
+    agent.sleep_timer.0 += delta_secs;
+    if agent.sleep_timer.0 >= config.time_to_sleep {
+        agent.sleep_timer.0 = 0.0;
+        agent.linvel.0 = Vec2::ZERO;
+        commands.entity(agent.entity).insert(AgentSleeping);
+    }
+}
+
+/// What the solve decided for an agent this tick.
+struct SolveOutcome {
+    /// The preferred and avoiding velocities, if the agent had somewhere to go.
+    velocities: Option<(Vec2, Vec2)>,
+    /// Whether the agent is within the tolerance of its goal.
+    arrived: bool,
+    /// Whether the agent and all its neighbours were idle.
+    idle: bool,
+    /// The neighbourhood fed to the solver, for agents recording it.
+    neighbourhood: Option<AvoidanceNeighbours>,
+    /// The velocity-space constraints, for agents recording them.
+    constraints: Option<AvoidanceConstraints>,
+}
+
+/// The state of an agent captured at the start of an avoidance pass.
+struct AgentSnapshot {
+    agent: Agent,
+    key: u64,
+    dynamic: bool,
+    solved_tick: u32,
+    sleeping: bool,
+    records_neighbours: bool,
+    records_constraints: bool,
+    group: Option<AvoidanceGroup>,
+}
+
+/// The resources configuring the avoidance pass.
+#[derive(SystemParam)]
+pub(crate) struct AvoidanceSettings<'w> {
+    mode: Res<'w, AvoidanceMode>,
+    lod: Res<'w, AvoidanceLod>,
+    sleep_config: Res<'w, AgentSleepConfig>,
+    side_config: Res<'w, SidePreferenceConfig>,
+    groups: Res<'w, GroupPolicy>,
+    obstacle_index: Res<'w, DodgyObstacleIndex>,
+    conversion: Res<'w, ObstacleConversion>,
+    inflation: Res<'w, ObstacleInflation>,
+    motion: Res<'w, ObstacleMotion>,
 }
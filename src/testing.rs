@@ -0,0 +1,260 @@
+//! A headless simulation for testing avoidance scenarios.
+//!
+//! [`SimHarness`] runs the avoidance with physics in deterministic mode, one fixed tick per
+//! step, and keeps track of the worst overlaps seen along the way so scenarios can assert
+//! on the whole run rather than on its last frame.
+
+use crate::agents::{AgentGoal, AgentInfo};
+use crate::geometry::{contains_point, distance_to_segment, obstacle_edges, signed_area};
+use crate::obstacles::{AsObstacle, DodgyObstacleIndex, ObstacleConversion, TransformObstacle};
+use crate::{AvoidanceMode, DodgyPlugin};
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use dodgy_2d::Obstacle;
+use std::time::Duration;
+
+/// The default duration of a tick, 64 ticks per second.
+pub const TIMESTEP: Duration = Duration::from_micros(15625);
+
+/// The deepest overlap between two entities seen during a run.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Overlap {
+    pub first: Entity,
+    pub second: Entity,
+    pub depth: f32,
+    pub tick: usize,
+}
+
+/// A `MinimalPlugins` app running physics and the [`DodgyPlugin`] without gravity.
+pub struct SimHarness {
+    app: App,
+    timestep: Duration,
+    ticks: usize,
+    agent_overlap: Option<Overlap>,
+    obstacle_overlap: Option<Overlap>,
+}
+
+impl Default for SimHarness {
+    fn default() -> Self {
+        SimHarness::new(TIMESTEP)
+    }
+}
+
+impl SimHarness {
+    pub fn new(timestep: Duration) -> SimHarness {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            TransformPlugin,
+            PhysicsPlugins::default(),
+            DodgyPlugin,
+        ))
+        .insert_resource(Gravity(Vec2::ZERO))
+        .insert_resource(AvoidanceMode::Deterministic)
+        .insert_resource(Time::<Fixed>::from_duration(timestep))
+        .insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+
+        SimHarness {
+            app,
+            timestep,
+            ticks: 0,
+            agent_overlap: None,
+            obstacle_overlap: None,
+        }
+    }
+
+    pub fn app(&mut self) -> &mut App {
+        &mut self.app
+    }
+
+    pub fn world(&mut self) -> &mut World {
+        self.app.world_mut()
+    }
+
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        self.app.world_mut().spawn(bundle).id()
+    }
+
+    /// Spawns a static collider at `position`.
+    pub fn spawn_obstacle(&mut self, position: Vec2, collider: Collider) -> Entity {
+        self.spawn((
+            RigidBody::Static,
+            collider,
+            Transform::from_translation(position.extend(0.0)),
+        ))
+    }
+
+    /// The simulated time, in seconds.
+    pub fn elapsed_secs(&self) -> f32 {
+        self.ticks as f32 * self.timestep.as_secs_f32()
+    }
+
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    /// Runs `ticks` fixed ticks, recording the overlaps after each of them.
+    pub fn step(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.app.update();
+            self.ticks += 1;
+            self.record_overlaps();
+        }
+    }
+
+    /// Steps until every agent reached its goal, for at most `secs` seconds, returning
+    /// whether they all did.
+    pub fn run_until_goals_reached(&mut self, secs: f32) -> bool {
+        while !self.goals_reached() {
+            if self.elapsed_secs() >= secs {
+                return false;
+            }
+            self.step(1);
+        }
+        true
+    }
+
+    /// Whether every agent with a goal is within its tolerance of it.
+    pub fn goals_reached(&mut self) -> bool {
+        self.unreached_goals().is_empty()
+    }
+
+    /// The agents farther from their goal than its tolerance.
+    pub fn unreached_goals(&mut self) -> Vec<Entity> {
+        let world = self.app.world_mut();
+        world
+            .query::<(Entity, &Transform, &AgentGoal)>()
+            .iter(world)
+            .filter(|(_, tf, goal)| tf.translation.xy().distance(goal.dest) > goal.tolerance)
+            .map(|(entity, _, _)| entity)
+            .collect()
+    }
+
+    /// The deepest overlap between two agents so far.
+    pub fn agent_overlap(&self) -> Option<Overlap> {
+        self.agent_overlap
+    }
+
+    /// The deepest an agent went into an obstacle so far.
+    pub fn obstacle_overlap(&self) -> Option<Overlap> {
+        self.obstacle_overlap
+    }
+
+    /// Panics if two agents ever overlapped by more than `epsilon`.
+    pub fn assert_no_agent_overlap(&self, epsilon: f32) {
+        if let Some(overlap) = self.agent_overlap.filter(|overlap| overlap.depth > epsilon) {
+            panic!(
+                "Agents {} and {} overlapped by {} on tick {}.",
+                overlap.first, overlap.second, overlap.depth, overlap.tick
+            );
+        }
+    }
+
+    /// Panics if an agent ever went into an obstacle by more than `epsilon`.
+    pub fn assert_no_obstacle_overlap(&self, epsilon: f32) {
+        if let Some(overlap) = self
+            .obstacle_overlap
+            .filter(|overlap| overlap.depth > epsilon)
+        {
+            panic!(
+                "Agent {} entered obstacle {} by {} on tick {}.",
+                overlap.first, overlap.second, overlap.depth, overlap.tick
+            );
+        }
+    }
+
+    /// Panics unless every agent reaches its goal within `secs` seconds of simulated time.
+    pub fn assert_goals_reached_within(&mut self, secs: f32) {
+        if !self.run_until_goals_reached(secs) {
+            panic!(
+                "{} agents didn't reach their goal within {secs} seconds.",
+                self.unreached_goals().len()
+            );
+        }
+    }
+
+    fn record_overlaps(&mut self) {
+        let tick = self.ticks;
+        let world = self.app.world_mut();
+        let agents: Vec<(Entity, Vec2, f32)> = world
+            .query::<(Entity, &Transform, &AgentInfo)>()
+            .iter(world)
+            .map(|(entity, tf, info)| (entity, tf.translation.xy(), info.radius))
+            .collect();
+
+        let conversion = world.resource::<ObstacleConversion>().clone();
+        let mut obstacles: Vec<(Entity, Obstacle)> = vec![];
+        for (entity, tf, collider, body) in world
+            .query_filtered::<(Entity, &Transform, &Collider, &RigidBody), Without<AgentInfo>>()
+            .iter(world)
+        {
+            if body.is_dynamic() {
+                continue;
+            }
+            for mut obstacle in collider.to_obstacles(&conversion) {
+                obstacle.transform_points(tf);
+                obstacles.push((entity, obstacle));
+            }
+        }
+        obstacles.extend(
+            world
+                .resource::<DodgyObstacleIndex>()
+                .iter()
+                .map(|(entity, entry)| (entity, entry.obstacle.clone())),
+        );
+
+        let deepest = |overlap: &mut Option<Overlap>, candidate: Overlap| {
+            if candidate.depth > 0.0 && !overlap.is_some_and(|o| o.depth >= candidate.depth) {
+                *overlap = Some(candidate);
+            }
+        };
+        for (i, (first, position, radius)) in agents.iter().enumerate() {
+            for (second, other, other_radius) in &agents[i + 1..] {
+                let depth = radius + other_radius - position.distance(*other);
+                deepest(
+                    &mut self.agent_overlap,
+                    Overlap {
+                        first: *first,
+                        second: *second,
+                        depth,
+                        tick,
+                    },
+                );
+            }
+            for (obstacle_entity, obstacle) in &obstacles {
+                deepest(
+                    &mut self.obstacle_overlap,
+                    Overlap {
+                        first: *first,
+                        second: *obstacle_entity,
+                        depth: penetration(obstacle, *position, *radius),
+                        tick,
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// How deep a disk is into an obstacle, negative when it is clear of it.
+///
+/// Only counter-clockwise closed obstacles have an inside; agents may stand within
+/// clockwise ones, like the bounds of a level.
+fn penetration(obstacle: &Obstacle, center: Vec2, radius: f32) -> f32 {
+    let distance = obstacle_edges(obstacle)
+        .into_iter()
+        .map(|(a, b)| distance_to_segment(center, a, b))
+        .fold(f32::INFINITY, f32::min);
+    let inside = match obstacle {
+        Obstacle::Closed { vertices } => {
+            signed_area(vertices) > 0.0 && contains_point(vertices, center)
+        }
+        Obstacle::Open { .. } => false,
+    };
+    if inside {
+        radius + distance
+    } else {
+        radius - distance
+    }
+}
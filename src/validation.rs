@@ -0,0 +1,248 @@
+use crate::agents::AgentInfo;
+use crate::geometry::{contains_point, obstacle_edges, signed_area};
+use crate::obstacles::{
+    AsObstacle, DodgyObstacle, DodgyObstacleIndex, ObstacleConversion, TransformObstacle,
+};
+use avian2d::prelude::*;
+use bevy::ecs::entity::EntityHashSet;
+use bevy::prelude::*;
+use dodgy_2d::Obstacle;
+use std::fmt;
+
+/// The distance under which two vertices are considered equal.
+const EPSILON: f32 = 1e-4;
+
+/// The order in which the vertices of a closed obstacle go around it.
+///
+/// Solid obstacles are counter-clockwise; clockwise obstacles keep agents inside them,
+/// like the bounds of a level.
+#[derive(Reflect, Clone, Copy, PartialEq, Eq, Debug)]
+#[reflect(PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Winding {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl Winding {
+    /// The winding of a polygon, or `None` if it has no area.
+    pub fn of(vertices: &[Vec2]) -> Option<Winding> {
+        let area = signed_area(vertices);
+        if area > 0.0 {
+            Some(Winding::CounterClockwise)
+        } else if area < 0.0 {
+            Some(Winding::Clockwise)
+        } else {
+            None
+        }
+    }
+}
+
+/// A problem that makes dodgy mishandle an obstacle, usually letting agents through it.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ObstacleIssue {
+    /// A closed obstacle with less than three vertices, or an open one with less than two.
+    TooFewVertices,
+
+    /// A closed obstacle whose vertices are all aligned.
+    ZeroArea,
+
+    /// A closed obstacle wound the other way than expected.
+    WrongWinding { expected: Winding },
+
+    /// An edge whose ends are the same point.
+    DegenerateEdge { index: usize },
+
+    /// Two non-consecutive vertices at the same position.
+    DuplicateVertex { first: usize, second: usize },
+
+    /// Two edges crossing or touching away from a shared vertex.
+    SelfIntersection { first: usize, second: usize },
+}
+
+impl fmt::Display for ObstacleIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObstacleIssue::TooFewVertices => write!(f, "too few vertices"),
+            ObstacleIssue::ZeroArea => write!(f, "zero area"),
+            ObstacleIssue::WrongWinding { expected } => {
+                write!(f, "wrong winding, expected {expected:?}")
+            }
+            ObstacleIssue::DegenerateEdge { index } => write!(f, "degenerate edge {index}"),
+            ObstacleIssue::DuplicateVertex { first, second } => {
+                write!(f, "vertices {first} and {second} are duplicates")
+            }
+            ObstacleIssue::SelfIntersection { first, second } => {
+                write!(f, "edges {first} and {second} intersect")
+            }
+        }
+    }
+}
+
+/// Checks an obstacle for the problems dodgy doesn't handle, expecting closed obstacles
+/// to be wound as `expected` when given.
+pub fn validate_obstacle(obstacle: &Obstacle, expected: Option<Winding>) -> Vec<ObstacleIssue> {
+    let (vertices, closed) = match obstacle {
+        Obstacle::Closed { vertices } => (vertices, true),
+        Obstacle::Open { vertices } => (vertices, false),
+    };
+    if vertices.len() < if closed { 3 } else { 2 } {
+        return vec![ObstacleIssue::TooFewVertices];
+    }
+
+    let mut issues = vec![];
+    if closed {
+        match (Winding::of(vertices), expected) {
+            (None, _) => issues.push(ObstacleIssue::ZeroArea),
+            (Some(winding), Some(expected)) if winding != expected => {
+                issues.push(ObstacleIssue::WrongWinding { expected });
+            }
+            _ => {}
+        }
+    }
+
+    let edges = obstacle_edges(obstacle);
+    for (index, (a, b)) in edges.iter().enumerate() {
+        if a.distance(*b) < EPSILON {
+            issues.push(ObstacleIssue::DegenerateEdge { index });
+        }
+    }
+
+    let consecutive =
+        |i: usize, j: usize| j == i + 1 || (closed && i == 0 && j == vertices.len() - 1);
+    for first in 0..vertices.len() {
+        for second in first + 1..vertices.len() {
+            if !consecutive(first, second) && vertices[first].distance(vertices[second]) < EPSILON {
+                issues.push(ObstacleIssue::DuplicateVertex { first, second });
+            }
+        }
+    }
+
+    let adjacent = |i: usize, j: usize| j == i + 1 || (closed && i == 0 && j == edges.len() - 1);
+    for first in 0..edges.len() {
+        for second in first + 1..edges.len() {
+            if !adjacent(first, second) && segments_intersect(edges[first], edges[second]) {
+                issues.push(ObstacleIssue::SelfIntersection { first, second });
+            }
+        }
+    }
+    issues
+}
+
+/// Reverses a closed obstacle wound the other way than `expected`, returning whether it
+/// did.
+pub fn correct_winding(obstacle: &mut Obstacle, expected: Winding) -> bool {
+    let Obstacle::Closed { vertices } = obstacle else {
+        return false;
+    };
+    match Winding::of(vertices) {
+        Some(winding) if winding != expected => {
+            vertices.reverse();
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Whether the `index`th obstacle of a collider is a hole, nested in an odd number of its
+/// other outlines.
+fn is_hole(obstacle: &Obstacle, index: usize, obstacles: &[Obstacle]) -> bool {
+    let Obstacle::Closed { vertices } = obstacle else {
+        return false;
+    };
+    let Some(vertex) = vertices.first() else {
+        return false;
+    };
+    let depth = obstacles
+        .iter()
+        .enumerate()
+        .filter(|(i, other)| match other {
+            Obstacle::Closed { vertices: outline } if *i != index => {
+                contains_point(outline, *vertex)
+            }
+            _ => false,
+        })
+        .count();
+    depth % 2 == 1
+}
+
+/// Whether two segments cross or touch.
+fn segments_intersect((a, b): (Vec2, Vec2), (c, d): (Vec2, Vec2)) -> bool {
+    let side = |p: Vec2, q: Vec2, r: Vec2| (q - p).perp_dot(r - p);
+    let within = |p: Vec2, q: Vec2, r: Vec2| r.cmpge(p.min(q)).all() && r.cmple(p.max(q)).all();
+
+    let d1 = side(c, d, a);
+    let d2 = side(c, d, b);
+    let d3 = side(a, b, c);
+    let d4 = side(a, b, d);
+    if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+    {
+        return true;
+    }
+    (d1 == 0.0 && within(c, d, a))
+        || (d2 == 0.0 && within(c, d, b))
+        || (d3 == 0.0 && within(a, b, c))
+        || (d4 == 0.0 && within(a, b, d))
+}
+
+/// Warns about the problems of static collider and [`DodgyObstacle`] obstacles when they
+/// change, once per entity.
+///
+/// Colliders are solid, so their outlines are expected counter-clockwise and the holes in
+/// them clockwise. The winding of a [`DodgyObstacle`] is left to its author.
+pub(crate) fn report_invalid_obstacles(
+    colliders: Query<
+        (Entity, &Transform, &Collider, &RigidBody),
+        (
+            Without<AgentInfo>,
+            Or<(Changed<Transform>, Changed<Collider>)>,
+        ),
+    >,
+    dodgy_obstacles: Query<
+        Entity,
+        (
+            With<DodgyObstacle>,
+            Or<(Changed<Transform>, Changed<DodgyObstacle>)>,
+        ),
+    >,
+    index: Res<DodgyObstacleIndex>,
+    conversion: Res<ObstacleConversion>,
+    mut reported: Local<EntityHashSet>,
+) {
+    let mut report = |entity: Entity, obstacle: &Obstacle, expected: Option<Winding>| {
+        if reported.contains(&entity) {
+            return;
+        }
+        let issues = validate_obstacle(obstacle, expected);
+        if issues.is_empty() {
+            return;
+        }
+        let issues: Vec<String> = issues.iter().map(ToString::to_string).collect();
+        warn!("Obstacle {entity} is invalid: {}.", issues.join(", "));
+        reported.insert(entity);
+    };
+
+    for (entity, tf, collider, body) in colliders.iter() {
+        if !body.is_static() {
+            continue;
+        }
+        let mut obstacles = collider.to_obstacles(&conversion);
+        for obstacle in obstacles.iter_mut() {
+            obstacle.transform_points(tf);
+        }
+        for (i, obstacle) in obstacles.iter().enumerate() {
+            let expected = if is_hole(obstacle, i, &obstacles) {
+                Winding::Clockwise
+            } else {
+                Winding::CounterClockwise
+            };
+            report(entity, obstacle, Some(expected));
+        }
+    }
+    for entity in dodgy_obstacles.iter() {
+        if let Some(entry) = index.get(entity) {
+            report(entity, &entry.obstacle, None);
+        }
+    }
+}
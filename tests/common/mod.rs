@@ -0,0 +1,31 @@
+//! Helpers shared by the integration tests.
+#![allow(dead_code)]
+
+use bevy::math::Vec2;
+use dodgy_2d::Obstacle;
+
+/// The vertices of closed obstacles, panicking on open ones.
+pub fn outlines(obstacles: Vec<Obstacle>) -> Vec<Vec<Vec2>> {
+    obstacles
+        .into_iter()
+        .map(|obstacle| match obstacle {
+            Obstacle::Closed { vertices } => vertices,
+            Obstacle::Open { .. } => panic!("expected closed obstacles"),
+        })
+        .collect()
+}
+
+/// The vertices of open obstacles, panicking on closed ones.
+pub fn chains(obstacles: Vec<Obstacle>) -> Vec<Vec<Vec2>> {
+    obstacles
+        .into_iter()
+        .map(|obstacle| match obstacle {
+            Obstacle::Open { vertices } => vertices,
+            Obstacle::Closed { .. } => panic!("expected open obstacles"),
+        })
+        .collect()
+}
+
+pub fn points(points: &[(f32, f32)]) -> Vec<Vec2> {
+    points.iter().map(|(x, y)| Vec2::new(*x, *y)).collect()
+}
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use bevy_dodgy::flocking::Flocking;
+use dodgy_2d::Agent;
+
+fn agent(position: Vec2, velocity: Vec2) -> Agent {
+    Agent {
+        position,
+        velocity,
+        radius: 1.0,
+        avoidance_responsibility: 1.0,
+    }
+}
+
+#[test]
+fn blend_stays_within_max_speed() {
+    let flocking = Flocking::new(1.0, 1.0, 1.0, 5.0);
+    let me = agent(Vec2::ZERO, Vec2::new(10.0, 0.0));
+    let mates = [
+        agent(Vec2::new(0.0, 4.0), Vec2::new(10.0, 0.0)),
+        agent(Vec2::new(2.0, 4.0), Vec2::new(10.0, 5.0)),
+    ];
+
+    // Alignment and cohesion add close to full speed on top of a preferred velocity at
+    // full speed.
+    let blended =
+        flocking.blend_preferred_velocity(&me, &[&mates[0], &mates[1]], Vec2::new(10.0, 0.0), 10.0);
+    assert!(blended.length() <= 10.0 + 1e-4, "{blended}");
+    assert!(blended.x > 0.0);
+}
+
+#[test]
+fn separation_pushes_away() {
+    let flocking = Flocking::new(1.0, 0.0, 0.0, 5.0);
+    let me = agent(Vec2::ZERO, Vec2::ZERO);
+    let mate = agent(Vec2::new(1.0, 0.0), Vec2::ZERO);
+
+    let blended = flocking.blend_preferred_velocity(&me, &[&mate], Vec2::ZERO, 10.0);
+    assert!(blended.abs_diff_eq(Vec2::new(-8.0, 0.0), 1e-4), "{blended}");
+}
+
+#[test]
+fn distant_agents_are_not_flockmates() {
+    let flocking = Flocking::default();
+    let me = agent(Vec2::ZERO, Vec2::ZERO);
+    let stranger = agent(Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0));
+
+    assert_eq!(
+        flocking.blend_preferred_velocity(&me, &[&stranger], Vec2::new(3.0, 0.0), 10.0),
+        Vec2::new(3.0, 0.0)
+    );
+}
@@ -0,0 +1,199 @@
+use bevy::math::Vec2;
+use bevy_dodgy::geometry::{
+    contains_point, cull_obstacle, inflate_obstacle, is_convex, merge_shared_edges, signed_area,
+    sweep_obstacle, CornerStyle,
+};
+use common::{chains, outlines, points};
+use dodgy_2d::Obstacle;
+
+mod common;
+
+fn unit_square() -> Obstacle {
+    Obstacle::Closed {
+        vertices: points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]),
+    }
+}
+
+fn assert_close(actual: &[Vec2], expected: &[Vec2]) {
+    assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+    for (a, b) in actual.iter().zip(expected) {
+        assert!(a.abs_diff_eq(*b, 1e-4), "{actual:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn merge_squares_sharing_an_edge() {
+    let squares = [
+        points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]),
+        points(&[(1.0, 0.0), (2.0, 0.0), (2.0, 1.0), (1.0, 1.0)]),
+    ];
+
+    // The shared edge cancels out and the collinear vertices left are dropped.
+    assert_eq!(
+        merge_shared_edges(&squares),
+        vec![points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)])]
+    );
+}
+
+#[test]
+fn merge_keeps_separate_polygons() {
+    let squares = [
+        points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)]),
+        points(&[(2.0, 0.0), (3.0, 0.0), (3.0, 1.0), (2.0, 1.0)]),
+    ];
+
+    assert_eq!(merge_shared_edges(&squares), squares.to_vec());
+}
+
+#[test]
+fn inflate_mitre_corners() {
+    let inflated = outlines(vec![inflate_obstacle(
+        &unit_square(),
+        1.0,
+        CornerStyle::Mitre { limit: 2.0 },
+        12,
+    )]);
+    assert_close(
+        &inflated[0],
+        &points(&[(-1.0, -1.0), (2.0, -1.0), (2.0, 2.0), (-1.0, 2.0)]),
+    );
+
+    // Right angles reach sqrt(2) times the distance, beyond a limit of 1, and get cut.
+    let bevelled = outlines(vec![inflate_obstacle(
+        &unit_square(),
+        1.0,
+        CornerStyle::Mitre { limit: 1.0 },
+        12,
+    )]);
+    assert_close(
+        &bevelled[0],
+        &points(&[
+            (-1.0, 0.0),
+            (0.0, -1.0),
+            (1.0, -1.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (-1.0, 1.0),
+        ]),
+    );
+}
+
+#[test]
+fn inflate_round_corners() {
+    let inflated = outlines(vec![inflate_obstacle(
+        &unit_square(),
+        1.0,
+        CornerStyle::Round,
+        16,
+    )]);
+    let outline = &inflated[0];
+
+    assert!(signed_area(outline) > 0.0);
+    // Four arcs of at least two segments, each vertex one unit away from the square.
+    assert!(outline.len() >= 12);
+    for vertex in outline {
+        let distance = vertex.distance(vertex.clamp(Vec2::ZERO, Vec2::ONE));
+        assert!((distance - 1.0).abs() < 1e-4, "{vertex} is {distance} away");
+    }
+    // The arcs end where the offset edges do.
+    for end in points(&[(0.0, -1.0), (1.0, -1.0), (2.0, 0.0), (2.0, 1.0)]) {
+        assert!(outline.iter().any(|vertex| vertex.abs_diff_eq(end, 1e-4)));
+    }
+}
+
+#[test]
+fn sweep_sliding_square() {
+    let swept = outlines(sweep_obstacle(
+        &unit_square(),
+        Vec2::new(0.5, 0.5),
+        Vec2::X,
+        0.0,
+        1.0,
+        2,
+    ));
+    assert_eq!(
+        swept,
+        vec![points(&[(0.0, 0.0), (2.0, 0.0), (2.0, 1.0), (0.0, 1.0)])]
+    );
+}
+
+#[test]
+fn sweep_rotating_bar() {
+    let bar = Obstacle::Closed {
+        vertices: points(&[(-2.0, -0.25), (2.0, -0.25), (2.0, 0.25), (-2.0, 0.25)]),
+    };
+    // A quarter turn over a second, sampled every eighth of a turn.
+    let swept = outlines(sweep_obstacle(
+        &bar,
+        Vec2::ZERO,
+        Vec2::ZERO,
+        std::f32::consts::FRAC_PI_2,
+        1.0,
+        2,
+    ));
+
+    assert_eq!(swept.len(), 1);
+    let hull = &swept[0];
+    assert!(signed_area(hull) > 0.0);
+    assert!(is_convex(hull));
+    // The hull covers the bar at rest, diagonal and upright, but not beyond its reach.
+    for inside in [
+        Vec2::new(1.9, 0.0),
+        Vec2::new(1.0, 1.0),
+        Vec2::new(0.0, 1.9),
+    ] {
+        assert!(contains_point(hull, inside), "{inside} isn't swept");
+    }
+    assert!(!contains_point(hull, Vec2::new(1.8, 1.8)));
+}
+
+#[test]
+fn sweep_open_obstacle() {
+    let fence = Obstacle::Open {
+        vertices: points(&[(0.0, 0.0), (1.0, 0.0)]),
+    };
+
+    // Open obstacles have no inside to sweep, so each placement is kept.
+    let swept = sweep_obstacle(&fence, Vec2::ZERO, Vec2::Y, 0.0, 1.0, 2);
+    assert_eq!(swept.len(), 3);
+    assert!(swept
+        .iter()
+        .all(|obstacle| matches!(obstacle, Obstacle::Open { .. })));
+}
+
+#[test]
+fn cull_long_wall() {
+    let wall = Obstacle::Closed {
+        vertices: points(&[(-100.0, 0.0), (100.0, 0.0), (100.0, 1.0), (-100.0, 1.0)]),
+    };
+
+    assert!(cull_obstacle(&wall, Vec2::ZERO, 200.0).is_none());
+
+    // Only the bottom side passes below the middle.
+    let middle = cull_obstacle(&wall, Vec2::new(0.0, -2.0), 2.5).unwrap();
+    assert_eq!(chains(middle), vec![points(&[(-100.0, 0.0), (100.0, 0.0)])]);
+
+    // Near the left end, the kept edges are chained around the corners.
+    let end = cull_obstacle(&wall, Vec2::new(-101.0, 0.5), 2.0).unwrap();
+    assert_eq!(
+        chains(end),
+        vec![points(&[
+            (100.0, 1.0),
+            (-100.0, 1.0),
+            (-100.0, 0.0),
+            (100.0, 0.0)
+        ])]
+    );
+}
+
+#[test]
+fn cull_open_obstacle() {
+    let fence = Obstacle::Open {
+        vertices: points(&[(0.0, 0.0), (10.0, 0.0), (20.0, 0.0), (30.0, 0.0)]),
+    };
+
+    let culled = cull_obstacle(&fence, Vec2::new(15.0, 1.0), 2.0).unwrap();
+    assert_eq!(chains(culled), vec![points(&[(10.0, 0.0), (20.0, 0.0)])]);
+}
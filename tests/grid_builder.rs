@@ -0,0 +1,130 @@
+use bevy::math::Vec2;
+use bevy_dodgy::geometry::{signed_area, GridObstacleBuilder};
+use common::{outlines, points};
+
+mod common;
+
+fn grid(width: usize, height: usize, solid: &[(usize, usize)]) -> GridObstacleBuilder {
+    let mut builder = GridObstacleBuilder::new(width, height, Vec2::ONE);
+    for (x, y) in solid {
+        builder.set(*x, *y, true);
+    }
+    builder
+}
+
+#[test]
+fn single_cell() {
+    let mut builder =
+        GridObstacleBuilder::new(1, 1, Vec2::new(2.0, 3.0)).with_origin(Vec2::new(10.0, 20.0));
+    builder.set(0, 0, true);
+
+    assert_eq!(
+        outlines(builder.build()),
+        vec![points(&[
+            (12.0, 20.0),
+            (12.0, 23.0),
+            (10.0, 23.0),
+            (10.0, 20.0)
+        ])]
+    );
+}
+
+#[test]
+fn empty_grid() {
+    assert!(grid(3, 3, &[]).build().is_empty());
+}
+
+#[test]
+fn l_shape() {
+    let l_shape = outlines(grid(2, 2, &[(0, 0), (1, 0), (0, 1)]).build());
+
+    // Collinear corners along the sides are dropped.
+    assert_eq!(
+        l_shape,
+        vec![points(&[
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 0.0),
+        ])]
+    );
+    assert_eq!(signed_area(&l_shape[0]), 6.0);
+}
+
+#[test]
+fn ring_with_hole() {
+    let ring: Vec<(usize, usize)> = (0..3)
+        .flat_map(|x| (0..3).map(move |y| (x, y)))
+        .filter(|cell| *cell != (1, 1))
+        .collect();
+    let ring = outlines(grid(3, 3, &ring).build());
+
+    assert_eq!(
+        ring,
+        vec![
+            points(&[(3.0, 0.0), (3.0, 3.0), (0.0, 3.0), (0.0, 0.0)]),
+            points(&[(1.0, 2.0), (2.0, 2.0), (2.0, 1.0), (1.0, 1.0)]),
+        ]
+    );
+    // The outer outline is counter-clockwise and the hole clockwise.
+    assert_eq!(signed_area(&ring[0]), 18.0);
+    assert_eq!(signed_area(&ring[1]), -2.0);
+}
+
+#[test]
+fn diagonal_cells_apart() {
+    for diagonal in [[(0, 0), (1, 1)], [(1, 0), (0, 1)]] {
+        let cells = outlines(grid(2, 2, &diagonal).build());
+
+        assert_eq!(cells.len(), 2);
+        for outline in &cells {
+            assert_eq!(outline.len(), 4);
+            assert_eq!(signed_area(outline), 2.0);
+        }
+    }
+}
+
+#[test]
+fn diagonal_cells_connected() {
+    let rising = outlines(
+        grid(2, 2, &[(0, 0), (1, 1)])
+            .with_connected_diagonals(true)
+            .build(),
+    );
+    assert_eq!(
+        rising,
+        vec![points(&[
+            (1.0, 0.0),
+            (1.0, 1.0),
+            (2.0, 1.0),
+            (2.0, 2.0),
+            (1.0, 2.0),
+            (1.0, 1.0),
+            (0.0, 1.0),
+            (0.0, 0.0),
+        ])]
+    );
+    assert_eq!(signed_area(&rising[0]), 4.0);
+
+    let falling = outlines(
+        grid(2, 2, &[(1, 0), (0, 1)])
+            .with_connected_diagonals(true)
+            .build(),
+    );
+    assert_eq!(
+        falling,
+        vec![points(&[
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 0.0),
+        ])]
+    );
+    assert_eq!(signed_area(&falling[0]), 4.0);
+}
@@ -0,0 +1,97 @@
+use bevy::prelude::*;
+use bevy_dodgy::groups::{AvoidanceGroup, GroupInteraction, GroupPolicy};
+use dodgy_2d::Agent;
+
+fn agent(avoidance_responsibility: f32) -> Agent {
+    Agent {
+        position: Vec2::ZERO,
+        velocity: Vec2::ZERO,
+        radius: 1.0,
+        avoidance_responsibility,
+    }
+}
+
+/// The share of the avoidance the solver gives an agent of `group` facing one of `other`.
+fn share(policy: &GroupPolicy, agent: &Agent, group: u32, other: &Agent, other_group: u32) -> f32 {
+    let Some(neighbour) = policy.apply(
+        agent,
+        Some(AvoidanceGroup(group)),
+        other,
+        Some(AvoidanceGroup(other_group)),
+    ) else {
+        return 0.0;
+    };
+    agent.avoidance_responsibility
+        / (agent.avoidance_responsibility + neighbour.avoidance_responsibility)
+}
+
+#[test]
+fn ratio_shares_the_avoidance() {
+    let mut policy = GroupPolicy::default();
+    policy.set(1, 2, GroupInteraction::Ratio(0.25));
+
+    for (a, b) in [(agent(1.0), agent(1.0)), (agent(2.0), agent(0.5))] {
+        let first = share(&policy, &a, 1, &b, 2);
+        let second = share(&policy, &b, 2, &a, 1);
+        assert!((first - 0.25).abs() < 1e-5, "{first}");
+        assert!((second - 0.75).abs() < 1e-5, "{second}");
+    }
+}
+
+#[test]
+fn yield_is_mirrored() {
+    let mut policy = GroupPolicy::default();
+    policy.set(1, 2, GroupInteraction::Yield);
+
+    // The yielding group takes all of the avoidance, and the other group none.
+    assert_eq!(
+        policy.interaction(Some(AvoidanceGroup(2)), Some(AvoidanceGroup(1))),
+        GroupInteraction::Ratio(0.0)
+    );
+    assert_eq!(share(&policy, &agent(1.0), 1, &agent(1.0), 2), 1.0);
+    assert!(policy
+        .apply(
+            &agent(1.0),
+            Some(AvoidanceGroup(2)),
+            &agent(1.0),
+            Some(AvoidanceGroup(1))
+        )
+        .is_none());
+}
+
+#[test]
+fn ignore_is_mirrored() {
+    let mut policy = GroupPolicy::default();
+    policy.set(1, 2, GroupInteraction::Ignore);
+
+    for (group, other) in [(1, 2), (2, 1)] {
+        assert!(policy
+            .apply(
+                &agent(1.0),
+                Some(AvoidanceGroup(group)),
+                &agent(1.0),
+                Some(AvoidanceGroup(other))
+            )
+            .is_none());
+    }
+}
+
+#[test]
+fn reciprocal_by_default() {
+    let mut policy = GroupPolicy::default();
+    policy.set(1, 2, GroupInteraction::Ignore);
+    let neighbour = agent(0.5);
+
+    // Agents without a group and pairs without a rule fall back to the default.
+    for (group, other) in [
+        (None, Some(AvoidanceGroup(2))),
+        (Some(AvoidanceGroup(1)), Some(AvoidanceGroup(3))),
+    ] {
+        assert_eq!(
+            policy
+                .apply(&agent(1.0), group, &neighbour, other)
+                .map(|n| n.avoidance_responsibility),
+            Some(0.5)
+        );
+    }
+}
@@ -0,0 +1,160 @@
+#![cfg(feature = "ldtk")]
+
+use bevy::math::Vec2;
+use bevy_dodgy::import::ldtk::LdtkImport;
+use bevy_dodgy::import::ImportError;
+use common::{outlines, points};
+
+mod common;
+
+/// A level at `world` holding a 3x2 IntGrid with the given values and entities.
+fn level(identifier: &str, world: (i32, i32), int_grid: &str, entities: &str) -> String {
+    format!(
+        r#"{{
+            "identifier": "{identifier}",
+            "worldX": {},
+            "worldY": {},
+            "layerInstances": [
+                {{
+                    "__identifier": "Entities",
+                    "__pxTotalOffsetX": 0,
+                    "__pxTotalOffsetY": 0,
+                    "__cWid": 0,
+                    "__gridSize": 16,
+                    "intGridCsv": [],
+                    "entityInstances": [{entities}]
+                }},
+                {{
+                    "__identifier": "Collisions",
+                    "__pxTotalOffsetX": 0,
+                    "__pxTotalOffsetY": 0,
+                    "__cWid": 3,
+                    "__gridSize": 16,
+                    "intGridCsv": [{int_grid}],
+                    "entityInstances": []
+                }}
+            ]
+        }}"#,
+        world.0, world.1
+    )
+}
+
+fn project(levels: &[String]) -> String {
+    format!(r#"{{ "levels": [{}] }}"#, levels.join(","))
+}
+
+/// The outline of two cells on the top row and one below the first, offset by `offset`.
+fn l_shape(offset: Vec2) -> Vec<Vec<Vec2>> {
+    let outline = points(&[
+        (16.0, -32.0),
+        (16.0, -16.0),
+        (32.0, -16.0),
+        (32.0, 0.0),
+        (0.0, 0.0),
+        (0.0, -32.0),
+    ]);
+    vec![outline.into_iter().map(|point| point + offset).collect()]
+}
+
+#[test]
+fn int_grid_merges_below_level() {
+    let text = project(&[level("Level_0", (0, 0), "1,1,0,1,0,0", "")]);
+
+    let obstacles = LdtkImport::default().parse(&text).unwrap();
+    assert_eq!(outlines(obstacles), l_shape(Vec2::ZERO));
+}
+
+#[test]
+fn levels_placed_y_up() {
+    let text = project(&[
+        level("Level_0", (0, 0), "0,0,0,0,0,0", ""),
+        level("Level_1", (64, 32), "1,1,0,1,0,0", ""),
+    ]);
+
+    let obstacles = LdtkImport::default().parse(&text).unwrap();
+    assert_eq!(outlines(obstacles), l_shape(Vec2::new(64.0, -32.0)));
+
+    let obstacles = LdtkImport::default()
+        .with_levels(["Level_1"])
+        .with_scale(2.0)
+        .with_origin(Vec2::new(10.0, 0.0))
+        .parse(&text)
+        .unwrap();
+    let scaled: Vec<Vec<Vec2>> = l_shape(Vec2::new(64.0, -32.0))
+        .into_iter()
+        .map(|outline| {
+            outline
+                .into_iter()
+                .map(|point| Vec2::new(10.0, 0.0) + point * 2.0)
+                .collect()
+        })
+        .collect();
+    assert_eq!(outlines(obstacles), scaled);
+}
+
+#[test]
+fn solid_values() {
+    let text = project(&[level("Level_0", (0, 0), "1,1,2,1,2,2", "")]);
+
+    let obstacles = LdtkImport::default()
+        .with_solid_values([1])
+        .parse(&text)
+        .unwrap();
+    assert_eq!(outlines(obstacles), l_shape(Vec2::ZERO));
+}
+
+#[test]
+fn entities_respect_pivot() {
+    let door = r#"{
+        "__identifier": "Door",
+        "__pivot": [0.5, 1],
+        "px": [40, 24],
+        "width": 16,
+        "height": 32
+    }"#;
+    let text = project(&[level("Level_0", (0, 0), "0,0,0,0,0,0", door)]);
+
+    assert!(LdtkImport::default().parse(&text).unwrap().is_empty());
+
+    // The pivot is at the bottom middle of the door.
+    let obstacles = LdtkImport::default()
+        .with_entities(["Door"])
+        .parse(&text)
+        .unwrap();
+    assert_eq!(
+        outlines(obstacles),
+        vec![points(&[
+            (32.0, -24.0),
+            (48.0, -24.0),
+            (48.0, 8.0),
+            (32.0, 8.0),
+        ])]
+    );
+}
+
+#[test]
+fn external_levels() {
+    let text = r#"{
+        "levels": [
+            { "identifier": "Level_0", "externalRelPath": "project/Level_0.ldtkl" }
+        ]
+    }"#;
+
+    assert!(matches!(
+        LdtkImport::default().parse(text),
+        Err(ImportError::Unsupported(_))
+    ));
+
+    let directory = std::env::temp_dir().join(format!("bevy_dodgy_ldtk_{}", std::process::id()));
+    std::fs::create_dir_all(directory.join("project")).unwrap();
+    std::fs::write(directory.join("project.ldtk"), text).unwrap();
+    std::fs::write(
+        directory.join("project/Level_0.ldtkl"),
+        level("Level_0", (0, 0), "1,1,0,1,0,0", ""),
+    )
+    .unwrap();
+
+    let obstacles = LdtkImport::default().load(directory.join("project.ldtk"));
+    std::fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(outlines(obstacles.unwrap()), l_shape(Vec2::ZERO));
+}
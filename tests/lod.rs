@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use bevy_dodgy::agents::{
+    AgentGoal, AgentInfo, AvoidanceOptionsComponent, AvoidanceResult, StableId,
+};
+use bevy_dodgy::lod::{AvoidanceFocus, AvoidanceLod};
+use bevy_dodgy::testing::SimHarness;
+
+fn lod(full_rate_distance: f32, agent_budget: Option<usize>) -> AvoidanceLod {
+    AvoidanceLod {
+        full_rate_distance,
+        buckets: 4,
+        agent_budget,
+    }
+}
+
+/// Spawns an agent heading far to the right, so that it never arrives during a test.
+fn spawn_agent(harness: &mut SimHarness, id: u64, position: Vec2) -> Entity {
+    harness.spawn((
+        AgentInfo::new(8.0, 30.0),
+        StableId(id),
+        AgentGoal::new(position + Vec2::new(1000.0, 0.0), 1.0),
+        Transform::from_translation(position.extend(0.0)),
+        AvoidanceOptionsComponent::new(0.5, 3.0, 1.0),
+    ))
+}
+
+fn solved_tick(harness: &mut SimHarness, agent: Entity) -> u32 {
+    harness
+        .world()
+        .get::<AvoidanceResult>(agent)
+        .unwrap()
+        .solved_tick
+}
+
+#[test]
+fn distant_agents_due_on_their_bucket() {
+    let lod = lod(10.0, None);
+
+    // Key 6 falls in bucket 2.
+    assert!(lod.is_due(6, 2, 1));
+    assert!(lod.is_due(6, 6, 5));
+    assert!(!lod.is_due(6, 3, 2));
+    assert!(!lod.is_due(6, 5, 2));
+
+    // An agent that missed its turn is due at once, even across the tick wrapping.
+    assert!(lod.is_due(6, 7, 2));
+    assert!(!lod.is_due(6, 1, u32::MAX - 1));
+    assert!(lod.is_due(6, 3, u32::MAX - 1));
+}
+
+#[test]
+fn single_bucket_always_due() {
+    let lod = AvoidanceLod {
+        buckets: 0,
+        ..lod(10.0, None)
+    };
+    assert!((0..4).all(|tick| lod.is_due(5, tick, tick)));
+}
+
+#[test]
+fn near_agents() {
+    let foci = [Vec2::ZERO, Vec2::new(100.0, 0.0)];
+    assert!(lod(10.0, None).is_near(Vec2::new(96.0, 3.0), &foci));
+    assert!(!lod(10.0, None).is_near(Vec2::new(50.0, 0.0), &foci));
+
+    // Without a full rate distance, every agent is near, even without foci.
+    assert!(AvoidanceLod::default().is_near(Vec2::new(1e6, 0.0), &[]));
+}
+
+#[test]
+fn budget_takes_turns() {
+    let mut harness = SimHarness::default();
+    harness.world().insert_resource(lod(f32::INFINITY, Some(1)));
+    let agents: Vec<Entity> = (0..3)
+        .map(|i| spawn_agent(&mut harness, i, Vec2::new(0.0, 100.0 * i as f32)))
+        .collect();
+
+    harness.step(10);
+
+    // One agent is solved per tick, the one that waited the longest.
+    let mut ticks: Vec<u32> = agents
+        .iter()
+        .map(|agent| solved_tick(&mut harness, *agent))
+        .collect();
+    ticks.sort();
+    assert_eq!(ticks, [ticks[0], ticks[0] + 1, ticks[0] + 2]);
+}
+
+#[test]
+fn budget_prefers_near_agents() {
+    let mut harness = SimHarness::default();
+    harness.world().insert_resource(lod(50.0, Some(1)));
+    harness.spawn((AvoidanceFocus, Transform::default()));
+    let near = spawn_agent(&mut harness, 0, Vec2::ZERO);
+    let far = spawn_agent(&mut harness, 1, Vec2::new(0.0, 500.0));
+
+    harness.step(10);
+    let last_solved = solved_tick(&mut harness, near);
+    harness.step(1);
+
+    assert_eq!(solved_tick(&mut harness, near), last_solved + 1);
+    assert_eq!(solved_tick(&mut harness, far), 0);
+}
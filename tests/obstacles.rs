@@ -0,0 +1,111 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_dodgy::geometry::signed_area;
+use bevy_dodgy::obstacles::{AsObstacle, ObstacleConversion, TransformObstacle};
+use common::{outlines, points};
+
+mod common;
+
+/// The world-space outlines of a collider on an entity with `tf`.
+fn world_outlines(mut collider: Collider, tf: Transform) -> Vec<Vec<Vec2>> {
+    // Avian copies the scale of the entity onto its collider.
+    collider.set_scale(tf.scale.xy(), 8);
+    let mut obstacles = collider.to_obstacles(&ObstacleConversion::default());
+    for obstacle in obstacles.iter_mut() {
+        obstacle.transform_points(&tf);
+    }
+    outlines(obstacles)
+}
+
+fn bounds(vertices: &[Vec2]) -> (Vec2, Vec2) {
+    let min = vertices.iter().copied().reduce(Vec2::min).unwrap();
+    let max = vertices.iter().copied().reduce(Vec2::max).unwrap();
+    (min, max)
+}
+
+#[test]
+fn scaled_collider() {
+    let outlines = world_outlines(
+        Collider::rectangle(2.0, 1.0),
+        Transform::from_xyz(10.0, 0.0, 0.0).with_scale(Vec3::splat(2.0)),
+    );
+
+    assert_eq!(outlines.len(), 1);
+    assert_eq!(
+        bounds(&outlines[0]),
+        (Vec2::new(8.0, -1.0), Vec2::new(12.0, 1.0))
+    );
+}
+
+#[test]
+fn mirrored_collider() {
+    let outlines = world_outlines(
+        Collider::triangle(Vec2::ZERO, Vec2::new(2.0, 0.0), Vec2::new(0.0, 1.0)),
+        Transform::from_scale(Vec3::new(-1.0, 1.0, 1.0)),
+    );
+
+    assert_eq!(outlines.len(), 1);
+    let mut vertices = outlines[0].clone();
+    vertices.sort_by(|a, b| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)));
+    assert_eq!(
+        vertices,
+        vec![
+            Vec2::new(-2.0, 0.0),
+            Vec2::new(0.0, 0.0),
+            Vec2::new(0.0, 1.0)
+        ]
+    );
+    // Mirroring keeps closed obstacles counter-clockwise.
+    assert!(signed_area(&outlines[0]) > 0.0);
+}
+
+#[test]
+fn unioned_compound() {
+    // Two overlapping cuboids forming an L.
+    let collider = Collider::compound(vec![
+        (
+            Position::from_xy(1.0, 0.5),
+            Rotation::default(),
+            Collider::rectangle(2.0, 1.0),
+        ),
+        (
+            Position::from_xy(0.5, 1.0),
+            Rotation::default(),
+            Collider::rectangle(1.0, 2.0),
+        ),
+    ]);
+    let conversion = ObstacleConversion {
+        compound_union_cell_size: Some(0.5),
+        ..default()
+    };
+
+    assert_eq!(
+        outlines(collider.to_obstacles(&conversion)),
+        vec![points(&[
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 0.0),
+        ])]
+    );
+}
+
+#[test]
+fn two_triangle_trimesh() {
+    let collider = Collider::trimesh(
+        vec![
+            Vec2::new(0.0, 0.0),
+            Vec2::new(1.0, 0.0),
+            Vec2::new(1.0, 1.0),
+            Vec2::new(0.0, 1.0),
+        ],
+        vec![[0, 1, 2], [0, 2, 3]],
+    );
+
+    assert_eq!(
+        outlines(collider.to_obstacles(&ObstacleConversion::default())),
+        vec![points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])]
+    );
+}
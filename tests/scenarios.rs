@@ -0,0 +1,88 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_dodgy::agents::{AgentGoal, AgentInfo, AvoidanceOptionsComponent, StableId};
+use bevy_dodgy::geometry::point_on_circle;
+use bevy_dodgy::testing::SimHarness;
+
+/// The overlap allowed between agents, as physics resolves contacts over a few ticks.
+const AGENT_EPSILON: f32 = 1.0;
+
+/// The depth agents may go into obstacles.
+const OBSTACLE_EPSILON: f32 = 0.5;
+
+fn spawn_agent(harness: &mut SimHarness, id: u64, from: Vec2, to: Vec2, radius: f32) {
+    harness.spawn((
+        AgentInfo::new(radius, 30.0),
+        StableId(id),
+        AgentGoal::new(to, 4.0),
+        Transform::from_translation(from.extend(0.0)),
+        AvoidanceOptionsComponent::new(0.5, 3.0, 1.0),
+    ));
+}
+
+#[test]
+fn circle_swap() {
+    let mut harness = SimHarness::default();
+    let count = 16;
+    for i in 0..count {
+        let theta = std::f32::consts::TAU * i as f32 / count as f32;
+        let from = point_on_circle((0.0, 0.0), 120.0, theta);
+        spawn_agent(&mut harness, i, from, -from, 8.0);
+    }
+
+    harness.assert_goals_reached_within(40.0);
+    harness.assert_no_agent_overlap(AGENT_EPSILON);
+}
+
+#[test]
+fn crossing_columns() {
+    let mut harness = SimHarness::default();
+    for i in 0..10 {
+        let y = -90.0 + 20.0 * i as f32;
+        spawn_agent(
+            &mut harness,
+            2 * i,
+            Vec2::new(-100.0, y),
+            Vec2::new(100.0, y),
+            8.0,
+        );
+        spawn_agent(
+            &mut harness,
+            2 * i + 1,
+            Vec2::new(100.0, y),
+            Vec2::new(-100.0, y),
+            8.0,
+        );
+    }
+
+    harness.assert_goals_reached_within(40.0);
+    harness.assert_no_agent_overlap(AGENT_EPSILON);
+}
+
+#[test]
+fn corridor() {
+    let mut harness = SimHarness::default();
+    harness.spawn_obstacle(Vec2::new(0.0, 50.0), Collider::rectangle(300.0, 20.0));
+    harness.spawn_obstacle(Vec2::new(0.0, -50.0), Collider::rectangle(300.0, 20.0));
+    for i in 0..4 {
+        let y = -15.0 + 10.0 * i as f32;
+        spawn_agent(
+            &mut harness,
+            2 * i,
+            Vec2::new(-200.0 - 15.0 * i as f32, y),
+            Vec2::new(200.0, y),
+            6.0,
+        );
+        spawn_agent(
+            &mut harness,
+            2 * i + 1,
+            Vec2::new(200.0 + 15.0 * i as f32, -y),
+            Vec2::new(-200.0, -y),
+            6.0,
+        );
+    }
+
+    harness.assert_goals_reached_within(60.0);
+    harness.assert_no_agent_overlap(AGENT_EPSILON);
+    harness.assert_no_obstacle_overlap(OBSTACLE_EPSILON);
+}
@@ -0,0 +1,57 @@
+use bevy::ecs::entity::EntityHashMap;
+use bevy::prelude::*;
+use bevy::scene::ron;
+use bevy::scene::serde::SceneDeserializer;
+use bevy_dodgy::agents::{AgentGoal, AgentInfo, AvoidanceOptionsComponent};
+use bevy_dodgy::DodgyPlugin;
+use serde::de::DeserializeSeed;
+
+fn dodgy_app() -> App {
+    let mut app = App::new();
+    app.add_plugins(DodgyPlugin);
+    app
+}
+
+#[test]
+fn agents_round_trip_through_scenes() {
+    let info = AgentInfo::new(12.0, 30.0).with_avoidance_responsibility(1.5);
+    let goal = AgentGoal::new(Vec2::new(-40.0, 25.0), 4.0);
+    let options = AvoidanceOptionsComponent::new(0.5, 2.5, 1.25);
+
+    let mut source = dodgy_app();
+    let agent = source
+        .world_mut()
+        .spawn((info.clone(), goal.clone(), options.clone()))
+        .id();
+    let scene = DynamicSceneBuilder::from_world(source.world())
+        .deny_all()
+        .allow::<AgentInfo>()
+        .allow::<AgentGoal>()
+        .allow::<AvoidanceOptionsComponent>()
+        .extract_entity(agent)
+        .build();
+    let registry = source.world().resource::<AppTypeRegistry>().read();
+    let text = scene.serialize(&registry).unwrap();
+
+    let mut target = dodgy_app();
+    let registry = target.world().resource::<AppTypeRegistry>().clone();
+    let mut deserializer = ron::de::Deserializer::from_str(&text).unwrap();
+    let scene = SceneDeserializer {
+        type_registry: &registry.read(),
+    }
+    .deserialize(&mut deserializer)
+    .unwrap();
+    let mut entity_map = EntityHashMap::default();
+    scene
+        .write_to_world(target.world_mut(), &mut entity_map)
+        .unwrap();
+
+    let loaded = entity_map[&agent];
+    let world = target.world();
+    assert_eq!(world.get::<AgentInfo>(loaded), Some(&info));
+    assert_eq!(world.get::<AgentGoal>(loaded), Some(&goal));
+    assert_eq!(
+        world.get::<AvoidanceOptionsComponent>(loaded),
+        Some(&options)
+    );
+}
@@ -0,0 +1,55 @@
+use bevy::prelude::*;
+use bevy_dodgy::side::{SidePreference, SidePreferenceConfig};
+use dodgy_2d::Agent;
+
+fn agent(position: Vec2, velocity: Vec2) -> Agent {
+    Agent {
+        position,
+        velocity,
+        radius: 1.0,
+        avoidance_responsibility: 1.0,
+    }
+}
+
+/// The preferred velocity of an agent heading right toward `neighbour`.
+fn bias(config: &SidePreferenceConfig, preference: SidePreference, neighbour: &Agent) -> Vec2 {
+    let agent = agent(Vec2::ZERO, Vec2::new(10.0, 0.0));
+    config.bias_preferred_velocity(preference, &agent, &[neighbour], Vec2::new(10.0, 0.0))
+}
+
+#[test]
+fn head_on_turns_to_the_preferred_side() {
+    let config = SidePreferenceConfig::default();
+    let oncoming = agent(Vec2::new(20.0, 0.0), Vec2::new(-10.0, 0.0));
+
+    // Keeping right turns clockwise, to negative y when heading along x.
+    let right = bias(&config, SidePreference::KeepRight, &oncoming);
+    assert!(right.y < 0.0);
+    assert!(right.abs_diff_eq(Vec2::from_angle(-config.max_bias_angle) * 10.0, 1e-4));
+
+    let left = bias(&config, SidePreference::KeepLeft, &oncoming);
+    assert!(left.y > 0.0);
+    assert!(left.abs_diff_eq(Vec2::new(right.x, -right.y), 1e-4));
+}
+
+#[test]
+fn culture_sides() {
+    let mut config = SidePreferenceConfig::default();
+    config.cultures.insert(1, SidePreference::KeepLeft);
+    let oncoming = agent(Vec2::new(20.0, 0.0), Vec2::new(-10.0, 0.0));
+
+    assert!(bias(&config, SidePreference::Culture(1), &oncoming).y > 0.0);
+    // Unknown cultures keep right.
+    assert!(bias(&config, SidePreference::Culture(2), &oncoming).y < 0.0);
+}
+
+#[test]
+fn crossing_neighbours_leave_velocity_alone() {
+    let config = SidePreferenceConfig::default();
+    let crossing = agent(Vec2::new(0.0, 20.0), Vec2::new(0.0, -10.0));
+
+    assert_eq!(
+        bias(&config, SidePreference::KeepRight, &crossing),
+        Vec2::new(10.0, 0.0)
+    );
+}
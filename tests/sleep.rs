@@ -0,0 +1,30 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_dodgy::agents::{AgentGoal, AgentInfo, AvoidanceOptionsComponent, StableId};
+use bevy_dodgy::sleep::AgentSleeping;
+use bevy_dodgy::testing::SimHarness;
+
+#[test]
+fn arrived_agent_sleeps_until_its_goal_changes() {
+    let mut harness = SimHarness::default();
+    let agent = harness.spawn((
+        AgentInfo::new(8.0, 30.0),
+        StableId(0),
+        AgentGoal::new(Vec2::new(60.0, 0.0), 4.0),
+        Transform::default(),
+        AvoidanceOptionsComponent::new(0.5, 3.0, 1.0),
+    ));
+
+    harness.assert_goals_reached_within(10.0);
+    harness.step(64);
+    let world = harness.world();
+    assert!(world.get::<AgentSleeping>(agent).is_some());
+    assert_eq!(world.get::<LinearVelocity>(agent).unwrap().0, Vec2::ZERO);
+    let position = world.get::<Transform>(agent).unwrap().translation.xy();
+    assert!(position.distance(Vec2::new(60.0, 0.0)) <= 4.0);
+
+    harness.world().get_mut::<AgentGoal>(agent).unwrap().dest = Vec2::ZERO;
+    harness.step(1);
+    assert!(harness.world().get::<AgentSleeping>(agent).is_none());
+    harness.assert_goals_reached_within(20.0);
+}
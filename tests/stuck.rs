@@ -0,0 +1,65 @@
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy_dodgy::agents::{AgentGoal, AgentInfo, AvoidanceOptionsComponent, StableId};
+use bevy_dodgy::formations::{Formation, FormationShape};
+use bevy_dodgy::stuck::{AgentStuck, StuckDetector};
+use bevy_dodgy::testing::SimHarness;
+
+#[derive(Resource, Default)]
+struct StuckAgents(Vec<Entity>);
+
+fn record_stuck_agents(mut events: EventReader<AgentStuck>, mut stuck: ResMut<StuckAgents>) {
+    stuck.0.extend(events.read().map(|event| event.entity));
+}
+
+fn harness() -> SimHarness {
+    let mut harness = SimHarness::default();
+    harness
+        .app()
+        .init_resource::<StuckAgents>()
+        .add_systems(PostUpdate, record_stuck_agents);
+    harness
+}
+
+#[test]
+fn blocked_formation_member_is_stuck() {
+    let mut harness = harness();
+    harness.spawn_obstacle(Vec2::new(40.0, 0.0), Collider::rectangle(20.0, 400.0));
+    let member = harness.spawn((
+        AgentInfo::new(8.0, 30.0),
+        StableId(0),
+        Transform::default(),
+        AvoidanceOptionsComponent::new(0.5, 3.0, 1.0),
+        StuckDetector::new(1.0, 5.0),
+    ));
+    // The slot moves on through the wall a little every tick while the member is held back.
+    harness.spawn((
+        Formation::new(FormationShape::Line, 20.0, 10.0).with_members([member]),
+        AgentGoal::new(Vec2::new(400.0, 0.0), 1.0),
+    ));
+
+    harness.step(4 * 64);
+
+    let stuck = &harness.world().resource::<StuckAgents>().0;
+    assert!(stuck.contains(&member));
+}
+
+#[test]
+fn formation_member_keeping_up_is_not_stuck() {
+    let mut harness = harness();
+    let member = harness.spawn((
+        AgentInfo::new(8.0, 30.0),
+        StableId(0),
+        Transform::default(),
+        AvoidanceOptionsComponent::new(0.5, 3.0, 1.0),
+        StuckDetector::new(1.0, 5.0),
+    ));
+    harness.spawn((
+        Formation::new(FormationShape::Line, 20.0, 10.0).with_members([member]),
+        AgentGoal::new(Vec2::new(400.0, 0.0), 1.0),
+    ));
+
+    harness.step(4 * 64);
+
+    assert!(harness.world().resource::<StuckAgents>().0.is_empty());
+}
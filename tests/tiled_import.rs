@@ -0,0 +1,203 @@
+#![cfg(feature = "tiled")]
+
+use bevy::math::Vec2;
+use bevy_dodgy::import::tiled::TiledImport;
+use bevy_dodgy::import::ImportError;
+use common::{outlines, points};
+
+mod common;
+
+/// A tileset whose first tile is fully solid and second tile has a solid left half.
+const TILESET: &str = r#"
+ <tileset firstgid="1" name="walls" tilewidth="16" tileheight="16" tilecount="2" columns="2">
+  <tile id="0">
+   <objectgroup draworder="index">
+    <object id="1" x="0" y="0" width="16" height="16"/>
+   </objectgroup>
+  </tile>
+  <tile id="1">
+   <objectgroup draworder="index">
+    <object id="1" x="0" y="0" width="8" height="16"/>
+   </objectgroup>
+  </tile>
+ </tileset>"#;
+
+fn map(infinite: bool, layers: &str) -> String {
+    format!(
+        r#"<map version="1.10" orientation="orthogonal" renderorder="right-down" width="3" height="2" tilewidth="16" tileheight="16" infinite="{}">{TILESET}{layers}</map>"#,
+        infinite as u8
+    )
+}
+
+/// The outline of two tiles on the top row and one below the first, below the origin.
+fn l_shape() -> Vec<Vec<Vec2>> {
+    vec![points(&[
+        (16.0, -32.0),
+        (16.0, -16.0),
+        (32.0, -16.0),
+        (32.0, 0.0),
+        (0.0, 0.0),
+        (0.0, -32.0),
+    ])]
+}
+
+#[test]
+fn csv_tiles_merge_below_origin() {
+    // The second tile is flipped, which doesn't matter for fully solid tiles.
+    let text = map(
+        false,
+        r#"<layer id="1" name="walls" width="3" height="2">
+            <data encoding="csv">
+1,2147483649,0,
+1,0,0
+</data>
+        </layer>"#,
+    );
+
+    let obstacles = TiledImport::default().parse(&text).unwrap();
+    assert_eq!(outlines(obstacles), l_shape());
+}
+
+#[test]
+fn base64_tiles() {
+    let text = map(
+        false,
+        r#"<layer id="1" name="walls" width="3" height="2">
+            <data encoding="base64">
+   AQAAAAEAAAAAAAAAAQAAAAAAAAAAAAAA
+</data>
+        </layer>"#,
+    );
+
+    let obstacles = TiledImport::default().parse(&text).unwrap();
+    assert_eq!(outlines(obstacles), l_shape());
+}
+
+#[test]
+fn xml_tiles_with_scale_and_origin() {
+    let text = map(
+        false,
+        r#"<layer id="1" name="walls" width="3" height="2">
+            <data>
+                <tile gid="1"/><tile gid="1"/><tile/>
+                <tile gid="1"/><tile/><tile/>
+            </data>
+        </layer>"#,
+    );
+
+    let obstacles = TiledImport::default()
+        .with_scale(2.0)
+        .with_origin(Vec2::new(100.0, 50.0))
+        .parse(&text)
+        .unwrap();
+    assert_eq!(
+        outlines(obstacles),
+        vec![points(&[
+            (132.0, -14.0),
+            (132.0, 18.0),
+            (164.0, 18.0),
+            (164.0, 50.0),
+            (100.0, 50.0),
+            (100.0, -14.0),
+        ])]
+    );
+}
+
+#[test]
+fn flipped_tile_shapes() {
+    // The half tile flipped horizontally, then vertically.
+    let text = map(
+        false,
+        r#"<layer id="1" name="walls" width="2" height="1">
+            <data encoding="base64">AgAAgAIAAEA=</data>
+        </layer>"#,
+    );
+
+    let obstacles = TiledImport::default().parse(&text).unwrap();
+    assert_eq!(
+        outlines(obstacles),
+        vec![
+            points(&[(16.0, 0.0), (8.0, 0.0), (8.0, -16.0), (16.0, -16.0)]),
+            points(&[(16.0, -16.0), (24.0, -16.0), (24.0, 0.0), (16.0, 0.0)]),
+        ]
+    );
+}
+
+#[test]
+fn infinite_map_chunks() {
+    let text = map(
+        true,
+        r#"<layer id="1" name="walls" width="3" height="2">
+            <data encoding="csv">
+                <chunk x="-2" y="0" width="2" height="1">1,1</chunk>
+                <chunk x="0" y="1" width="2" height="1">0,1</chunk>
+            </data>
+        </layer>"#,
+    );
+
+    let obstacles = TiledImport::default().parse(&text).unwrap();
+    assert_eq!(
+        outlines(obstacles),
+        vec![
+            points(&[(32.0, -32.0), (32.0, -16.0), (16.0, -16.0), (16.0, -32.0)]),
+            points(&[(0.0, -16.0), (0.0, 0.0), (-32.0, 0.0), (-32.0, -16.0)]),
+        ]
+    );
+}
+
+#[test]
+fn object_rectangles_y_up() {
+    let text = map(
+        false,
+        r#"<objectgroup id="2" name="objects">
+            <object id="1" x="10" y="20" width="30" height="40"/>
+            <object id="2" x="0" y="0"><point/></object>
+        </objectgroup>"#,
+    );
+
+    let obstacles = TiledImport::default()
+        .with_scale(2.0)
+        .with_origin(Vec2::new(100.0, 0.0))
+        .parse(&text)
+        .unwrap();
+    assert_eq!(
+        outlines(obstacles),
+        vec![points(&[
+            (120.0, -120.0),
+            (180.0, -120.0),
+            (180.0, -40.0),
+            (120.0, -40.0),
+        ])]
+    );
+}
+
+#[test]
+fn selected_layers() {
+    let text = map(
+        false,
+        r#"<layer id="1" name="decoration" width="3" height="2">
+            <data encoding="csv">1,1,1,1,1,1</data>
+        </layer>
+        <layer id="2" name="walls" width="3" height="2">
+            <data encoding="csv">1,1,0,1,0,0</data>
+        </layer>"#,
+    );
+
+    let obstacles = TiledImport::default()
+        .with_layers(["walls"])
+        .parse(&text)
+        .unwrap();
+    assert_eq!(outlines(obstacles), l_shape());
+}
+
+#[test]
+fn external_tileset_needs_a_path() {
+    let text = r#"<map orientation="orthogonal" width="1" height="1" tilewidth="16" tileheight="16">
+        <tileset firstgid="1" source="walls.tsx"/>
+    </map>"#;
+
+    assert!(matches!(
+        TiledImport::default().parse(text),
+        Err(ImportError::Unsupported(_))
+    ));
+}
@@ -0,0 +1,74 @@
+use bevy::math::Vec2;
+use bevy_dodgy::validation::{correct_winding, validate_obstacle, ObstacleIssue, Winding};
+use common::points;
+use dodgy_2d::Obstacle;
+
+mod common;
+
+fn square() -> Vec<Vec2> {
+    points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+}
+
+#[test]
+fn valid_square() {
+    let obstacle = Obstacle::Closed { vertices: square() };
+    assert!(validate_obstacle(&obstacle, Some(Winding::CounterClockwise)).is_empty());
+}
+
+#[test]
+fn bowtie_intersects_itself() {
+    let obstacle = Obstacle::Closed {
+        vertices: points(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)]),
+    };
+
+    let issues = validate_obstacle(&obstacle, None);
+    assert!(issues.contains(&ObstacleIssue::SelfIntersection {
+        first: 0,
+        second: 2
+    }));
+}
+
+#[test]
+fn degenerate_obstacles() {
+    let line = Obstacle::Closed {
+        vertices: points(&[(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]),
+    };
+    assert!(validate_obstacle(&line, None).contains(&ObstacleIssue::ZeroArea));
+
+    let point = Obstacle::Open {
+        vertices: points(&[(0.0, 0.0)]),
+    };
+    assert_eq!(
+        validate_obstacle(&point, None),
+        vec![ObstacleIssue::TooFewVertices]
+    );
+
+    let repeated = Obstacle::Open {
+        vertices: points(&[(0.0, 0.0), (1.0, 0.0), (1.0, 0.0), (2.0, 1.0)]),
+    };
+    assert!(
+        validate_obstacle(&repeated, None).contains(&ObstacleIssue::DegenerateEdge { index: 1 })
+    );
+}
+
+#[test]
+fn winding() {
+    let mut clockwise: Vec<Vec2> = square();
+    clockwise.reverse();
+    let mut obstacle = Obstacle::Closed {
+        vertices: clockwise,
+    };
+    assert_eq!(
+        validate_obstacle(&obstacle, Some(Winding::CounterClockwise)),
+        vec![ObstacleIssue::WrongWinding {
+            expected: Winding::CounterClockwise
+        }]
+    );
+
+    assert!(correct_winding(&mut obstacle, Winding::CounterClockwise));
+    assert!(!correct_winding(&mut obstacle, Winding::CounterClockwise));
+    let Obstacle::Closed { vertices } = &obstacle else {
+        unreachable!();
+    };
+    assert_eq!(Winding::of(vertices), Some(Winding::CounterClockwise));
+}